@@ -2,6 +2,7 @@ use std::{
     collections::{
         BinaryHeap,
         HashMap,
+        HashSet,
         VecDeque,
     },
     fmt::Write as _,
@@ -15,6 +16,7 @@ use chrono::{
     format::StrftimeItems,
     NaiveDateTime,
 };
+use clap::Args;
 use smallvec::SmallVec;
 
 use crate::{
@@ -22,18 +24,39 @@ use crate::{
         Event,
         EventLoop,
         EventLoopContext,
+        EventLoopMetrics,
         EventLoopState,
         EventWrapper,
+        Periodic,
+        ShutdownSignal,
+        SHUTDOWN_MESSAGE,
     },
     machine::{
-        get_machines,
         Machine,
+        NetworkMap,
     },
     netscript::{
         Date,
         NsWrapper,
     },
-    script_deploy::HGW,
+    port::PortChannel,
+    port_opener::get_available_openers,
+    servers::{
+        ServerBudget,
+        ServerChange,
+    },
+    script_deploy::{
+        deploy_child_scripts,
+        ChildReport,
+        DeployManifest,
+        HGWOptions,
+        HGW,
+    },
+    stock::{
+        get_symbol,
+        Position,
+        StockBias,
+    },
     time_consts::{
         MILLISECOND,
         SECOND,
@@ -46,12 +69,131 @@ use crate::{
 
 const RESERVATION_RATE: f64 = 0.9;
 
-pub async fn auto_hack(ns: &NsWrapper<'_>) {
-    // disable logging. at all.
-    ns.disable_log("ALL");
+/// How often finished child scripts are removed from the running process
+/// lists.
+const REAP_INTERVAL: f64 = SECOND * 5.;
+/// Random delay added to each reaping so it does not always coincide with the
+/// general poll.
+const REAP_JITTER: f64 = MILLISECOND * 500.;
+
+/// Port the child scripts write their reports to.
+const CHILD_REPORT_CHANNEL: PortChannel<ChildReport> = PortChannel::new(19);
+/// How often the child script reports are read.
+const CHILD_REPORT_INTERVAL: f64 = MILLISECOND * 200.;
+
+/// How often our stock positions are read in stock mode. This is about as
+/// often as the stock market updates.
+const STOCK_POSITION_INTERVAL: f64 = SECOND * 6.;
+
+/// How often servers are bought and upgraded in server mode.
+const MANAGE_SERVERS_INTERVAL: f64 = SECOND * 10.;
+
+/// Port listened to by a running autohack for `SHUTDOWN_MESSAGE`.
+const SHUTDOWN_PORT: usize = 20;
+/// File on the autohack host that makes a running autohack shut down.
+const SHUTDOWN_FLAG_FILE: &str = "autohack_stop.txt";
+
+#[derive(Args, Debug, Clone)]
+pub struct AutoHackMode {
+    #[arg(
+        long,
+        conflicts_with_all = ["stop", "kill_on_exit", "stock", "servers"],
+        help = "kill every child script on the network then exit"
+    )]
+    cleanup: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["kill_on_exit", "stock", "servers"],
+        help = "ask the running autohack to shut down then exit"
+    )]
+    stop: bool,
+    #[arg(long, help = "kill the spawned child scripts when shutting down")]
+    kill_on_exit: bool,
+    #[arg(
+        long,
+        help = "grow the targets whose stock we own and hack those we shorted \
+                to move their prices our way"
+    )]
+    stock: bool,
+    #[arg(long, help = "buy and upgrade servers to hack from as money allows")]
+    servers: bool,
+    #[command(flatten)]
+    server_budget: ServerBudget,
+}
 
-    let mut ahg = EventLoop::new(AutoHackGovernor::new(ns));
-    ahg.run(ns).await;
+impl AutoHackMode {
+    pub async fn execute(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) {
+        if self.stop {
+            ns.write_port(SHUTDOWN_PORT, SHUTDOWN_MESSAGE);
+            ns.tprint("Shutdown requested.");
+
+            return;
+        }
+
+        if self.cleanup {
+            kill_child_scripts(ns);
+
+            return;
+        }
+
+        // disable logging. at all.
+        ns.disable_log("ALL");
+
+        // reports left over from a previous run would be counted against the
+        // batches of this one, since batch ids start over
+        CHILD_REPORT_CHANNEL.clear(ns);
+
+        let mut governor = AutoHackGovernor::new(ns);
+        governor.kill_on_exit = self.kill_on_exit;
+        governor.stock_aware = self.stock;
+        governor.server_budget =
+            self.servers.then(|| self.server_budget.clone());
+
+        let mut ahg = EventLoop::new(governor);
+        ahg.add_shutdown_signal(ShutdownSignal::Port(SHUTDOWN_PORT));
+        ahg.add_shutdown_signal(ShutdownSignal::FlagFile(
+            SHUTDOWN_FLAG_FILE.to_owned(),
+        ));
+        ahg.run(ns).await;
+    }
+}
+
+/// Kills every instance of the HGW scripts on every rooted machine.
+fn kill_child_scripts(ns: &NsWrapper<'_>) {
+    let mut print_str = "\n".to_owned();
+
+    for machine in NetworkMap::load(ns).iter() {
+        if !machine.is_root(ns) {
+            continue;
+        }
+
+        let killed = [HGW::Hack, HGW::Grow, HGW::Weaken]
+            .iter()
+            .filter(|hgw| {
+                ns.script_kill(hgw.script().filename, machine.get_hostname())
+            })
+            .count();
+
+        if 0 < killed {
+            writeln!(
+                &mut print_str,
+                "{}: killed {} script(s)",
+                machine.get_hostname(),
+                killed
+            )
+            .unwrap();
+        }
+    }
+
+    if print_str == "\n" {
+        ns.tprint("No child scripts running.");
+    }
+    else {
+        ns.tprint(&print_str);
+    }
 }
 
 #[derive(Debug)]
@@ -59,6 +201,11 @@ enum AutoHackEventType {
     PollTarget(u64),
     MemoryFreed,
     GeneralPoll,
+    ReapProcesses,
+    ReadChildReports,
+    ChildFinished(ChildReport),
+    RefreshStockPositions,
+    ManageServers,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -109,6 +256,62 @@ impl AutoHackEventWrapped {
             event_type: AutoHackEventType::GeneralPoll,
         }
     }
+
+    pub fn new_read_child_reports(
+        trigger_time: f64,
+        grace_period: f64,
+    ) -> AutoHackEventWrapped {
+        AutoHackEventWrapped {
+            trigger_time,
+            grace_period,
+            event_type: AutoHackEventType::ReadChildReports,
+        }
+    }
+
+    pub fn new_child_finished(
+        trigger_time: f64,
+        grace_period: f64,
+        report: ChildReport,
+    ) -> AutoHackEventWrapped {
+        AutoHackEventWrapped {
+            trigger_time,
+            grace_period,
+            event_type: AutoHackEventType::ChildFinished(report),
+        }
+    }
+
+    pub fn new_refresh_stock_positions(
+        trigger_time: f64,
+        grace_period: f64,
+    ) -> AutoHackEventWrapped {
+        AutoHackEventWrapped {
+            trigger_time,
+            grace_period,
+            event_type: AutoHackEventType::RefreshStockPositions,
+        }
+    }
+
+    pub fn new_manage_servers(
+        trigger_time: f64,
+        grace_period: f64,
+    ) -> AutoHackEventWrapped {
+        AutoHackEventWrapped {
+            trigger_time,
+            grace_period,
+            event_type: AutoHackEventType::ManageServers,
+        }
+    }
+
+    pub fn new_reap_processes(
+        trigger_time: f64,
+        grace_period: f64,
+    ) -> AutoHackEventWrapped {
+        AutoHackEventWrapped {
+            trigger_time,
+            grace_period,
+            event_type: AutoHackEventType::ReapProcesses,
+        }
+    }
 }
 
 impl Event for AutoHackEventWrapped {
@@ -119,6 +322,38 @@ impl Event for AutoHackEventWrapped {
     fn grace_period(&self) -> f64 {
         self.grace_period
     }
+
+    fn priority(&self) -> i32 {
+        use AutoHackEventType::*;
+
+        // finished processes and freed memory have to be accounted for before
+        // anything gets polled on the same millisecond, and diagnostics
+        // should come after everything else
+        match self.event_type {
+            ReapProcesses |
+            ReadChildReports |
+            ChildFinished(_) |
+            RefreshStockPositions => 2,
+            MemoryFreed => 1,
+            PollTarget(_) => 0,
+            GeneralPoll | ManageServers => -1,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        use AutoHackEventType::*;
+
+        match self.event_type {
+            PollTarget(_) => "PollTarget",
+            MemoryFreed => "MemoryFreed",
+            GeneralPoll => "GeneralPoll",
+            ReapProcesses => "ReapProcesses",
+            ReadChildReports => "ReadChildReports",
+            ChildFinished(_) => "ChildFinished",
+            RefreshStockPositions => "RefreshStockPositions",
+            ManageServers => "ManageServers",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -147,6 +382,41 @@ impl TargetState {
 struct RunningProcessMetadata {
     pid: usize,
     threads: usize,
+    hgw: HGW,
+    /// Position of the action inside its batch.
+    index: usize,
+}
+
+/// Identifies a single action of a batch.
+///
+/// These are passed to the child scripts so that two otherwise identical
+/// spawns on the same host never collide as duplicate script instances.
+#[derive(Debug, Clone, Copy)]
+struct BatchTag {
+    batch_id: u64,
+    index: usize,
+}
+
+/// A single action of a batch, to be spread over the hackers.
+#[derive(Debug, Clone, Copy)]
+struct SpawnRequest {
+    hgw: HGW,
+    /// When the child scripts are spawned.
+    spawn_time: f64,
+    /// When the action should start. It lands a fixed duration later.
+    run_time: f64,
+    threads: usize,
+    split_type: SplitType,
+    tag: BatchTag,
+}
+
+/// The processes spawned by a single poll of a target.
+#[derive(Debug)]
+struct RunningBatch {
+    id: u64,
+    /// The spawn time, not the finish time.
+    spawn_time: f64,
+    processes: SmallVec<[RunningProcessMetadata; 4]>,
 }
 
 #[derive(Eq, PartialEq, Clone, Copy, Debug)]
@@ -231,11 +501,23 @@ struct TargetStateBundle {
 
     // latest spawned at the front
     // earliest spawned at the back
-    // first element is the spawn time, not the finish time
-    // TODO: we need a way to clear this
-    running_pids: VecDeque<(f64, SmallVec<[RunningProcessMetadata; 4]>)>,
+    // finished processes are removed by reap_finished_processes()
+    running_pids: VecDeque<RunningBatch>,
+    // the first and last batch ids spawned against this target
+    issued_batches: Option<(u64, u64)>,
 
     last_poll: f64,
+
+    // the stock of the organization owning the target, if any, and which way
+    // we want its price to go
+    symbol: Option<&'static str>,
+    stock_bias: StockBias,
+
+    // statistics from the child script reports
+    last_landed: f64,
+    money_stolen: f64,
+    hacks_landed: usize,
+    hacks_failed: usize,
 }
 
 impl TargetStateBundle {
@@ -281,12 +563,33 @@ impl TargetStateBundle {
             None => write!(writable, "Never polled")?,
         };
 
+        let last_landed = NaiveDateTime::from_timestamp_millis(
+            self.last_landed as i64,
+        )
+        .map(|ndt| ndt.format_with_items(StrftimeItems::new("%H:%M:%S%.3f")));
+
+        match last_landed {
+            Some(st) => write!(writable, " | {}", st)?,
+            None => write!(writable, " | Never landed")?,
+        };
+
         write!(
             writable,
-            " | {: >6.4}% | +{: >6.3}% |",
+            " | {: >6.4}% | +{: >6.3}% | {: >3} batches {: >5}h {: >5}g \
+             {: >5}w |",
             money_available as f64 / self.machine.get_max_money() as f64 * 100.,
             self.machine.get_security_level(ns)
                 - self.machine.get_min_security(),
+            self.running_pids.len(),
+            self.get_in_flight_threads(HGW::Hack),
+            self.get_in_flight_threads(HGW::Grow),
+            self.get_in_flight_threads(HGW::Weaken),
+        )?;
+
+        write!(
+            writable,
+            " ${: >12.0} | {: >5}/{: <5} hacks failed |",
+            self.money_stolen, self.hacks_failed, self.hacks_landed,
         )
     }
 
@@ -301,14 +604,121 @@ impl TargetStateBundle {
         let weakens_required = machine.get_weaken_threads_to_reduce(ns);
 
         TargetStateBundle {
-            machine,
             state: TargetState::TotalWeaken(weakens_required),
             is_waiting_for_memory: false,
             running_pids: Default::default(),
+            issued_batches: None,
             last_poll: f64::MIN,
+
+            symbol: get_symbol(machine.get_organization_name()),
+            stock_bias: StockBias::Neutral,
+
+            last_landed: f64::MIN,
+            money_stolen: 0.,
+            hacks_landed: 0,
+            hacks_failed: 0,
+
+            machine,
+        }
+    }
+
+    /// Removes the processes that are no longer running. Returns the number
+    /// of processes removed.
+    fn reap_finished_processes(
+        &mut self,
+        ns: &NsWrapper<'_>,
+    ) -> usize {
+        let mut reaped = 0;
+
+        for batch in self.running_pids.iter_mut() {
+            let before = batch.processes.len();
+            batch.processes.retain(|metadata| ns.is_running(metadata.pid));
+            reaped += before - batch.processes.len();
+        }
+
+        self.running_pids.retain(|batch| !batch.processes.is_empty());
+
+        reaped
+    }
+
+    /// Reads our position on the stock of the target, if any.
+    fn refresh_stock_bias(
+        &mut self,
+        ns: &NsWrapper<'_>,
+    ) {
+        self.stock_bias = self
+            .symbol
+            .and_then(|symbol| Position::get(ns, symbol))
+            .map(|position| StockBias::from_position(&position))
+            .unwrap_or_default();
+    }
+
+    fn push_batch(
+        &mut self,
+        batch: RunningBatch,
+    ) {
+        self.issued_batches = match self.issued_batches {
+            Some((first, _)) => Some((first, batch.id)),
+            None => Some((batch.id, batch.id)),
+        };
+
+        self.running_pids.push_front(batch);
+    }
+
+    /// Takes note of a finished child script.
+    fn on_child_finished(
+        &mut self,
+        report: &ChildReport,
+    ) {
+        let is_issued = self.issued_batches.is_some_and(|(first, last)| {
+            (first..=last).contains(&report.batch)
+        });
+
+        // spawned by someone else, or before a restart
+        if !is_issued {
+            return;
+        }
+
+        // the process is done, so it doesn't have to wait for the reaping.
+        // late reports find their batch reaped already.
+        if let Some(batch) =
+            self.running_pids.iter_mut().find(|b| b.id == report.batch)
+        {
+            batch.processes.retain(|metadata| {
+                metadata.pid != report.pid || metadata.index != report.index
+            });
+        }
+        self.running_pids.retain(|batch| !batch.processes.is_empty());
+
+        self.last_landed = self.last_landed.max(report.landed);
+
+        if report.action == HGW::Hack {
+            self.hacks_landed += 1;
+            self.money_stolen += report.result;
+
+            // a failed hack steals nothing
+            if report.result == 0. {
+                self.hacks_failed += 1;
+            }
         }
     }
 
+    /// Returns the number of threads of the given script still running
+    /// against this target.
+    ///
+    /// This is only as accurate as the last reaping.
+    fn get_in_flight_threads(
+        &self,
+        hgw: HGW,
+    ) -> usize {
+        self.running_pids
+            .iter()
+            .flat_map(|batch| batch.processes.iter())
+            .filter(|metadata| metadata.hgw == hgw)
+            .map(|metadata| metadata.threads)
+            .sum()
+    }
+
     fn get_earliest_allowable_weaken_spawn(
         &self,
         grace_period: f64,
@@ -316,7 +726,7 @@ impl TargetStateBundle {
         let latest = self
             .running_pids
             .iter()
-            .map(|(time, _)| time)
+            .map(|batch| batch.spawn_time)
             .max_by(|a, b| a.partial_cmp(b).unwrap());
 
         // requires get_latest_weaken_release
@@ -329,13 +739,18 @@ impl TargetStateBundle {
     fn spawn_hgw(
         &mut self,
         ns: &NsWrapper<'_>,
-        hgw: HGW,
         hackers: AHGHackerIterator,
-        current_time: f64,
-        run_time: f64,
-        threads: usize,
-        split_type: SplitType,
+        request: SpawnRequest,
     ) -> Option<SmallVec<[RunningProcessMetadata; 4]>> {
+        let SpawnRequest {
+            hgw,
+            spawn_time,
+            run_time,
+            threads,
+            split_type,
+            tag,
+        } = request;
+
         // if there are no available hackers to run our job, don't do it
         let hackers =
             match find_available_hackers(ns, hackers, threads, split_type) {
@@ -349,20 +764,39 @@ impl TargetStateBundle {
 
         // the run time is different from spawn time
         // the spawn time is immediate.
-        // the run time is spawn time + sleep time
-        // we have to determine how much time to sleep
-
-        let sleep_time = run_time - current_time;
-        let sleep_time_str = format!("{}", sleep_time);
+        // the run time is spawn time + additional msec
+        // the duration of the action is fixed at spawn time, so the action
+        // lands at run time + duration
+        let additional_msec = run_time - spawn_time;
+        let port_str = format!("{}", CHILD_REPORT_CHANNEL.get_port());
+        let batch_str = format!("{}", tag.batch_id);
+        let index_str = format!("{}", tag.index);
         let mut metadatas = SmallVec::<[RunningProcessMetadata; 4]>::new();
 
         for (hacker, threads) in hackers {
+            let options = HGWOptions {
+                additional_msec,
+                threads,
+                stock: self.stock_bias.influences(hgw),
+            };
+
+            let mut args = vec![self.machine.get_hostname().to_owned()];
+            args.extend(options.args());
+            args.extend([
+                "--port".to_owned(),
+                port_str.clone(),
+                "--batch".to_owned(),
+                batch_str.clone(),
+                "--index".to_owned(),
+                index_str.clone(),
+            ]);
+
             let maybe_pid = ns
                 .exec(
                     hgw.script().filename,
                     hacker.get_hostname(),
                     Some(threads),
-                    &[self.machine.get_hostname(), &sleep_time_str],
+                    &args,
                 )
                 .unwrap();
 
@@ -383,6 +817,8 @@ impl TargetStateBundle {
             let metadata = RunningProcessMetadata {
                 pid,
                 threads,
+                hgw,
+                index: tag.index,
             };
 
             metadatas.push(metadata);
@@ -405,6 +841,13 @@ impl TargetStateBundle {
 
         self.last_poll = now;
 
+        // every poll spawns at most one batch
+        let batch_id = govr.next_batch_id();
+        let tag = |index| BatchTag {
+            batch_id,
+            index,
+        };
+
         match self.state.clone() {
             TotalWeaken(weakens_left) => {
                 if weakens_left == 0 {
@@ -417,12 +860,15 @@ impl TargetStateBundle {
                 // spawn weaken
                 let maybe_pid_meta = self.spawn_hgw(
                     ns,
-                    HGW::Weaken,
                     govr.get_hackers_iter(),
-                    now,
-                    now,
-                    weakens_left,
-                    PartialSplit,
+                    SpawnRequest {
+                        hgw: HGW::Weaken,
+                        spawn_time: now,
+                        run_time: now,
+                        threads: weakens_left,
+                        split_type: PartialSplit,
+                        tag: tag(0),
+                    },
                 );
 
                 let pid_meta = match maybe_pid_meta {
@@ -436,13 +882,17 @@ impl TargetStateBundle {
                 ctx.add_event(AutoHackEventWrapped::new_memory_freed(
                     now + hack_time * 4. + 5.,
                     50.,
-                ));
+                ))
+                .unwrap_or_else(|e| report_rejected_event(ns, e));
 
                 let new_weakens_left = weakens_left
                     - pid_meta.iter().map(|meta| meta.threads).sum::<usize>();
 
-                // TODO: make sure that running_pids does not grow too large
-                self.running_pids.push_front((now, pid_meta));
+                self.push_batch(RunningBatch {
+                    id: batch_id,
+                    spawn_time: now,
+                    processes: pid_meta,
+                });
 
                 // spawn another one grace period later. this will happen
                 // regardless if it's finished or not
@@ -452,7 +902,8 @@ impl TargetStateBundle {
                     now + MILLISECOND * 50. * 2.,
                     MILLISECOND * 50.,
                     self.get_hash(),
-                ));
+                ))
+                .unwrap_or_else(|e| report_rejected_event(ns, e));
 
                 // update the state
                 self.state = TotalWeaken(new_weakens_left);
@@ -460,8 +911,7 @@ impl TargetStateBundle {
 
             MaxGrow => {
                 // calculate how many grow and weakens we need to do
-                let mut grows_required =
-                    get_potential_grow_amt(ns, &self.machine);
+                let grows_required = get_potential_grow_amt(ns, &self.machine);
 
                 if grows_required == 0 {
                     self.state = Hack;
@@ -470,6 +920,22 @@ impl TargetStateBundle {
                     return;
                 }
 
+                // don't grow more than what the grows that are yet to land
+                // will take care of. wait for them instead.
+                let mut grows_required = grows_required
+                    .saturating_sub(self.get_in_flight_threads(HGW::Grow));
+
+                if grows_required == 0 {
+                    ctx.add_event(AutoHackEventWrapped::new_poll_target(
+                        now + hack_time * 4. + MILLISECOND * 50.,
+                        MILLISECOND * 50.,
+                        self.get_hash(),
+                    ))
+                    .unwrap_or_else(|e| report_rejected_event(ns, e));
+
+                    return;
+                }
+
                 let mut new_pids = SmallVec::new();
 
                 // attempt to spawn multiple grows
@@ -497,12 +963,16 @@ impl TargetStateBundle {
                     // spawn the grow half
                     match self.spawn_hgw(
                         ns,
-                        HGW::Grow,
                         govr.get_hackers_iter(),
-                        now,
-                        now + (4. - 3.2) * hack_time - MILLISECOND * 50.,
-                        grows_required,
-                        NoSplit, // NEVER split grows.
+                        SpawnRequest {
+                            hgw: HGW::Grow,
+                            spawn_time: now,
+                            run_time: now + (4. - 3.2) * hack_time -
+                                MILLISECOND * 50.,
+                            threads: grows_required,
+                            split_type: NoSplit, // NEVER split grows.
+                            tag: tag(0),
+                        },
                     ) {
                         Some(m) => new_pids.extend(m.into_iter()),
                         None => on_failure!(),
@@ -511,12 +981,15 @@ impl TargetStateBundle {
                     // spawn the weaken half
                     match self.spawn_hgw(
                         ns,
-                        HGW::Weaken,
                         govr.get_hackers_iter(),
-                        now,
-                        now,
-                        weakens_required,
-                        PartialSplit,
+                        SpawnRequest {
+                            hgw: HGW::Weaken,
+                            spawn_time: now,
+                            run_time: now,
+                            threads: weakens_required,
+                            split_type: PartialSplit,
+                            tag: tag(1),
+                        },
                     ) {
                         Some(m) => new_pids.extend(m.into_iter()),
                         None => on_failure!(),
@@ -530,9 +1003,14 @@ impl TargetStateBundle {
                 ctx.add_event(AutoHackEventWrapped::new_memory_freed(
                     now + hack_time * 4. + 5.,
                     50.,
-                ));
+                ))
+                .unwrap_or_else(|e| report_rejected_event(ns, e));
 
-                self.running_pids.push_front((now, new_pids));
+                self.push_batch(RunningBatch {
+                    id: batch_id,
+                    spawn_time: now,
+                    processes: new_pids,
+                });
 
                 ctx.add_event(AutoHackEventWrapped::new_poll_target(
                     // TODO: there should be a proper place where you get the
@@ -540,7 +1018,8 @@ impl TargetStateBundle {
                     now + hack_time * 4. + MILLISECOND * 50.,
                     MILLISECOND * 50.,
                     self.get_hash(),
-                ));
+                ))
+                .unwrap_or_else(|e| report_rejected_event(ns, e));
             },
 
             Hack => {
@@ -551,12 +1030,15 @@ impl TargetStateBundle {
                 // spawn the hack
                 match self.spawn_hgw(
                     ns,
-                    HGW::Hack,
                     govr.get_hackers_iter(),
-                    now,
-                    now + (4. - 1.) * hack_time - 50.,
-                    1,
-                    PartialSplit,
+                    SpawnRequest {
+                        hgw: HGW::Hack,
+                        spawn_time: now,
+                        run_time: now + (4. - 1.) * hack_time - 50.,
+                        threads: 1,
+                        split_type: PartialSplit,
+                        tag: tag(0),
+                    },
                 ) {
                     Some(pids) => new_pids.extend(pids.into_iter()),
                     None => {
@@ -569,12 +1051,15 @@ impl TargetStateBundle {
                 // spawn the first weaken
                 match self.spawn_hgw(
                     ns,
-                    HGW::Weaken,
                     govr.get_hackers_iter(),
-                    now,
-                    now,
-                    1,
-                    PartialSplit,
+                    SpawnRequest {
+                        hgw: HGW::Weaken,
+                        spawn_time: now,
+                        run_time: now,
+                        threads: 1,
+                        split_type: PartialSplit,
+                        tag: tag(1),
+                    },
                 ) {
                     Some(pids) => new_pids.extend(pids.into_iter()),
                     None => {
@@ -587,12 +1072,15 @@ impl TargetStateBundle {
                 // spawn the grow
                 match self.spawn_hgw(
                     ns,
-                    HGW::Grow,
                     govr.get_hackers_iter(),
-                    now,
-                    now + (4. - 3.2) * hack_time + 50.,
-                    1,
-                    PartialSplit,
+                    SpawnRequest {
+                        hgw: HGW::Grow,
+                        spawn_time: now,
+                        run_time: now + (4. - 3.2) * hack_time + 50.,
+                        threads: 1,
+                        split_type: PartialSplit,
+                        tag: tag(2),
+                    },
                 ) {
                     Some(pids) => new_pids.extend(pids.into_iter()),
                     None => {
@@ -605,12 +1093,15 @@ impl TargetStateBundle {
                 // spawn the second weaken
                 match self.spawn_hgw(
                     ns,
-                    HGW::Weaken,
                     govr.get_hackers_iter(),
-                    now,
-                    now + 50. * 2.,
-                    1,
-                    PartialSplit,
+                    SpawnRequest {
+                        hgw: HGW::Weaken,
+                        spawn_time: now,
+                        run_time: now + 50. * 2.,
+                        threads: 1,
+                        split_type: PartialSplit,
+                        tag: tag(3),
+                    },
                 ) {
                     Some(pids) => new_pids.extend(pids.into_iter()),
                     None => {
@@ -623,16 +1114,22 @@ impl TargetStateBundle {
                 ctx.add_event(AutoHackEventWrapped::new_memory_freed(
                     now + hack_time * 4. + 5. + 50. * 2.,
                     50.,
-                ));
+                ))
+                .unwrap_or_else(|e| report_rejected_event(ns, e));
 
-                self.running_pids.push_front((now, new_pids));
+                self.push_batch(RunningBatch {
+                    id: batch_id,
+                    spawn_time: now,
+                    processes: new_pids,
+                });
 
                 // spawn another that will hach this machine again
                 ctx.add_event(AutoHackEventWrapped::new_poll_target(
                     now + 4. + MILLISECOND * 50.,
                     MILLISECOND * 50.,
                     self.get_hash(),
-                ));
+                ))
+                .unwrap_or_else(|e| report_rejected_event(ns, e));
             },
         }
     }
@@ -678,6 +1175,10 @@ struct AutoHackGovernor {
     targets_by_score: Vec<u64>,
 
     hacking_level: usize,
+    kill_on_exit: bool,
+    stock_aware: bool,
+    server_budget: Option<ServerBudget>,
+    last_batch_id: u64,
 }
 
 impl AutoHackGovernor {
@@ -687,32 +1188,25 @@ impl AutoHackGovernor {
             targets_by_name: HashMap::new(),
             targets_by_score: vec![],
             hacking_level: ns.get_player_hacking_level(),
+            kill_on_exit: false,
+            stock_aware: false,
+            server_budget: None,
+            // start from the current time so that batch ids don't collide
+            // with those of child scripts left over by a previous run
+            last_batch_id: Date::now() as u64,
         };
 
         ahg.regenerate_hackers_and_targets(ns);
         ahg
     }
 
-    /// Returns a list of hostname hashes currently used as a hacker and a
+    /// Returns the set of hostname hashes currently used as a hacker and a
     /// target.
-    fn get_used_hostname_hashes(
-        &self,
-        mut buffer: Vec<u64>,
-    ) -> Vec<u64> {
-        buffer.clear();
-
+    fn get_used_hostname_hashes(&self) -> HashSet<u64> {
         let iter_1 = self.targets_by_name.keys().cloned();
         let iter_2 = self.hackers.iter().map(|h| get_machine_hash(h));
 
-        for val in iter_1.chain(iter_2) {
-            buffer.push(val);
-        }
-
-        // sort then dedup
-        buffer.sort_unstable();
-        buffer.dedup();
-
-        buffer
+        iter_1.chain(iter_2).collect()
     }
 
     /// Returns a list of machines that are neither in current list of hackers
@@ -720,26 +1214,24 @@ impl AutoHackGovernor {
     fn get_new_machines(
         &self,
         ns: &NsWrapper<'_>,
-        buffer_1: Vec<u64>,
     ) -> Vec<(u64, Machine)> {
-        let used_hostnames = self.get_used_hostname_hashes(buffer_1);
+        let used_hostnames = self.get_used_hostname_hashes();
+        let openers = get_available_openers(ns);
 
-        let hacking_level = ns.get_player_hacking_level();
-
-        get_machines(ns)
+        NetworkMap::load(ns)
+            .into_machines()
             .into_iter()
-            .filter_map(|mut m| match crate::scan::nuke_machine(ns, &mut m) {
-                crate::scan::NukeResult::NotNuked => None,
-                _ => Some(m)
-            })
-            //.filter(|m| m.get_min_hacking_skill() <= hacking_level)
             .map(|m| (get_machine_hash(&m), m))
             // don't allow machines that already exist in hackers and targets
             // so we don't consume ns function runtime
-            .filter(|(h, _)| !used_hostnames
-                .iter()
-                .any(|uhn| *uhn == *h)
-            )
+            .filter(|(h, _)| !used_hostnames.contains(h))
+            .filter_map(|(h, mut m)| {
+                match crate::scan::nuke_machine(ns, &mut m, &openers) {
+                    crate::scan::NukeResult::JustNuked |
+                    crate::scan::NukeResult::WasNuked => Some((h, m)),
+                    _ => None,
+                }
+            })
             // the machines must be rooted
             // TODO: just root it ourselves
             .filter(|(_, m)| m.is_root(ns))
@@ -750,15 +1242,10 @@ impl AutoHackGovernor {
     fn get_new_hackers_from(
         &mut self,
         ns: &NsWrapper<'_>,
+        manifest: &mut DeployManifest,
         machines: &[(u64, Machine)],
         buffer: &mut Vec<Arc<Machine>>,
     ) {
-        use crate::script_deploy::{
-            GROW_SCRIPT,
-            HACK_SCRIPT,
-            WEAKEN_SCRIPT,
-        };
-
         buffer.clear();
 
         // add the new entries into the buffer first
@@ -767,13 +1254,7 @@ impl AutoHackGovernor {
             .map(|(_, m)| m)
             .filter(|m| 0 < m.get_max_gb_ram_hundredths(ns))
             // only allow hackers that can possess this file
-            .filter_map(|h| {
-                let deployed = WEAKEN_SCRIPT.deploy_to_machine(ns, h, true) &&
-                    GROW_SCRIPT.deploy_to_machine(ns, h, true) &&
-                    HACK_SCRIPT.deploy_to_machine(ns, h, true);
-
-                deployed.then(|| h)
-            })
+            .filter(|h| deploy_child_scripts(ns, manifest, h))
             .cloned()
             .map(|m| Arc::new(m));
         buffer.extend(iter);
@@ -854,17 +1335,29 @@ impl AutoHackGovernor {
     ) {
         // TODO: make sure that these buffers come from the current object
         // itself
-        let buffer_1 = vec![];
-
-        let new_machines = self.get_new_machines(ns, buffer_1);
+        let new_machines = self.get_new_machines(ns);
 
         let mut buffer_3 = vec![];
         let mut buffer_4 = vec![];
 
-        self.get_new_hackers_from(ns, &new_machines, &mut buffer_3);
+        let mut manifest = DeployManifest::load(ns);
+        self.get_new_hackers_from(
+            ns,
+            &mut manifest,
+            &new_machines,
+            &mut buffer_3,
+        );
+        manifest.save(ns);
+
         self.get_new_targets_from(ns, &new_machines, &mut buffer_4);
     }
 
+    /// Returns a batch id that has never been used by this governor.
+    fn next_batch_id(&mut self) -> u64 {
+        self.last_batch_id += 1;
+        self.last_batch_id
+    }
+
     /// Obtains an iterator over hackers.
     ///
     /// This iterator is especially created to always return the same value
@@ -904,8 +1397,12 @@ impl AutoHackGovernor {
                 continue;
             }
 
+            // weakens that are yet to land already count toward the total
             target.state = TargetState::TotalWeaken(
-                target.machine.get_weaken_threads_to_reduce(ns),
+                target
+                    .machine
+                    .get_weaken_threads_to_reduce(ns)
+                    .saturating_sub(target.get_in_flight_threads(HGW::Weaken)),
             );
         }
 
@@ -916,6 +1413,7 @@ impl AutoHackGovernor {
     fn do_diagnostics(
         &self,
         ns: &NsWrapper<'_>,
+        metrics: &EventLoopMetrics,
     ) {
         let mut printable = String::new();
 
@@ -933,7 +1431,7 @@ impl AutoHackGovernor {
             })
             .unwrap();
 
-        write!(&mut printable, "Current time: {}", strftime);
+        write!(&mut printable, "Current time: {}\n\n{}", strftime, metrics);
 
         ns.clear_log();
         ns.print(&printable);
@@ -945,7 +1443,7 @@ impl EventLoopState for AutoHackGovernor {
 
     fn initial_run<'a>(
         &mut self,
-        _ns: &NsWrapper<'a>,
+        ns: &NsWrapper<'a>,
         ctx: &mut EventLoopContext<Self::Event>,
     ) {
         // poll each target near immediately
@@ -958,16 +1456,89 @@ impl EventLoopState for AutoHackGovernor {
                 name.clone(),
             );
 
-            ctx.add_event(event);
+            ctx.add_event(event)
+                .unwrap_or_else(|e| report_rejected_event(ns, e));
+        }
+
+        // create a poll to update the level every second
+        let periodic = Periodic::new(next_second, SECOND, |trigger_time| {
+            AutoHackEventWrapped::new_general_poll(
+                trigger_time,
+                MILLISECOND * 50.,
+            )
+        });
+
+        if ctx.add_periodic_event(periodic).is_err() {
+            ns.tprint("rejected general poll with invalid timings");
+        }
+
+        // periodically forget about the child scripts that have finished
+        let periodic =
+            Periodic::new(next_second, REAP_INTERVAL, |trigger_time| {
+                AutoHackEventWrapped::new_reap_processes(
+                    trigger_time,
+                    MILLISECOND * 50.,
+                )
+            })
+            .with_jitter(REAP_JITTER);
+
+        if ctx.add_periodic_event(periodic).is_err() {
+            ns.tprint("rejected process reaping with invalid timings");
         }
 
-        // create a poll to update the level
-        let event = AutoHackEventWrapped::new_general_poll(
+        // periodically turn the child script reports into events
+        let periodic = Periodic::new(
             next_second,
-            MILLISECOND * 50.,
+            CHILD_REPORT_INTERVAL,
+            |trigger_time| {
+                AutoHackEventWrapped::new_read_child_reports(
+                    trigger_time,
+                    MILLISECOND * 50.,
+                )
+            },
         );
 
-        ctx.add_event(event);
+        if ctx.add_periodic_event(periodic).is_err() {
+            ns.tprint("rejected child report reading with invalid timings");
+        }
+
+        // keep track of which way we want the stocks of the targets to go
+        if self.stock_aware {
+            let periodic = Periodic::new(
+                Date::now(),
+                STOCK_POSITION_INTERVAL,
+                |trigger_time| {
+                    AutoHackEventWrapped::new_refresh_stock_positions(
+                        trigger_time,
+                        MILLISECOND * 50.,
+                    )
+                },
+            );
+
+            if ctx.add_periodic_event(periodic).is_err() {
+                ns.tprint(
+                    "rejected stock position refresh with invalid timings",
+                );
+            }
+        }
+
+        // grow our own pool of hackers
+        if self.server_budget.is_some() {
+            let periodic = Periodic::new(
+                next_second,
+                MANAGE_SERVERS_INTERVAL,
+                |trigger_time| {
+                    AutoHackEventWrapped::new_manage_servers(
+                        trigger_time,
+                        MILLISECOND * 500.,
+                    )
+                },
+            );
+
+            if ctx.add_periodic_event(periodic).is_err() {
+                ns.tprint("rejected server management with invalid timings");
+            }
+        }
     }
 
     fn on_event<'a>(
@@ -1002,14 +1573,64 @@ impl EventLoopState for AutoHackGovernor {
             },
 
             GeneralPoll => {
+                // this is re-armed by the event loop
                 self.do_level_up_check(ns);
-                self.do_diagnostics(ns);
+                self.do_diagnostics(ns, ctx.metrics());
+            },
 
-                // spawn another general poll request
-                ctx.add_event(AutoHackEventWrapped::new_general_poll(
-                    Date::now() + SECOND,
-                    MILLISECOND * 50.,
-                ));
+            ReapProcesses => {
+                // this is re-armed by the event loop
+                for target in self.targets_by_name.values_mut() {
+                    target.reap_finished_processes(ns);
+                }
+            },
+
+            ReadChildReports => {
+                // this is re-armed by the event loop
+                let now = Date::now();
+
+                for report in CHILD_REPORT_CHANNEL.drain(ns) {
+                    ctx.add_event(AutoHackEventWrapped::new_child_finished(
+                        now,
+                        MILLISECOND * 50.,
+                        report,
+                    ))
+                    .unwrap_or_else(|e| report_rejected_event(ns, e));
+                }
+            },
+
+            RefreshStockPositions => {
+                // this is re-armed by the event loop
+                for target in self.targets_by_name.values_mut() {
+                    target.refresh_stock_bias(ns);
+                }
+            },
+
+            ManageServers => {
+                // this is re-armed by the event loop
+                let Some(budget) = &self.server_budget
+                else {
+                    return;
+                };
+
+                // upgraded servers are picked up on their own since their
+                // ram is always read live, but new ones have to be added
+                let purchased = budget
+                    .manage_servers(ns)
+                    .iter()
+                    .any(|c| matches!(c, ServerChange::Purchased { .. }));
+
+                if purchased {
+                    self.regenerate_hackers_and_targets(ns);
+                }
+            },
+
+            ChildFinished(report) => {
+                let key = get_hostname_hash(&report.target);
+
+                if let Some(target) = self.targets_by_name.get_mut(&key) {
+                    target.on_child_finished(&report);
+                }
             },
         }
     }
@@ -1029,8 +1650,33 @@ impl EventLoopState for AutoHackGovernor {
         &self,
         ns: &NsWrapper<'a>,
         event_heap: &BinaryHeap<EventWrapper<Self::Event>>,
+        _metrics: &EventLoopMetrics,
     ) {
     }
+
+    fn on_shutdown<'a>(
+        &mut self,
+        ns: &NsWrapper<'a>,
+        _event_heap: &BinaryHeap<EventWrapper<Self::Event>>,
+    ) {
+        if !self.kill_on_exit {
+            ns.print("Shutting down. Child scripts are left running.");
+            return;
+        }
+
+        let mut killed = 0;
+        for target in self.targets_by_name.values_mut() {
+            for batch in target.running_pids.drain(..) {
+                killed += batch
+                    .processes
+                    .into_iter()
+                    .filter(|metadata| ns.kill(metadata.pid as i32))
+                    .count();
+            }
+        }
+
+        ns.print(&format!("Shutting down. Killed {} child scripts.", killed));
+    }
 }
 
 /// Iterator over the list of hackers.
@@ -1122,7 +1768,18 @@ fn kill_all(
     }
 }
 
+fn report_rejected_event(
+    ns: &NsWrapper<'_>,
+    event: AutoHackEventWrapped,
+) {
+    ns.tprint(&format!("rejected event with invalid trigger time: {:?}", event));
+}
+
 fn get_machine_hash(machine: &Machine) -> u64 {
+    get_hostname_hash(machine.get_hostname())
+}
+
+fn get_hostname_hash(hostname: &str) -> u64 {
     use std::{
         collections::hash_map::DefaultHasher,
         hash::Hasher as _,
@@ -1130,6 +1787,6 @@ fn get_machine_hash(machine: &Machine) -> u64 {
 
     let mut hasher = DefaultHasher::new();
 
-    hasher.write(machine.get_hostname().as_bytes());
+    hasher.write(hostname.as_bytes());
     hasher.finish()
 }
@@ -1,11 +1,48 @@
 /** @param {NS} ns */
 export async function main(ns) {
-    let flags = ns.flags([]);
-    let sleep_time = Number(flags["_"][1]);
+    let flags = ns.flags([
+        ["delay", 0],
+        ["threads", 0],
+        ["stock", false],
+        ["port", 0],
+        ["batch", 0],
+        ["index", 0],
+    ]);
+    let target = flags["_"][0];
 
-    if (0 < sleep_time) {
-        await ns.sleep(sleep_time);
+    // the duration is fixed when called, not when the delay is over
+    let options = {
+        additionalMsec: Math.max(0, Math.round(flags["delay"])),
+        stock: flags["stock"],
+    };
+
+    if (0 < flags["threads"]) {
+        options.threads = flags["threads"];
+    }
+
+    let result = await ns.grow(target, options);
+
+    // report back to the governor, if it asked for it
+    if (0 < flags["port"]) {
+        let report = JSON.stringify({
+            pid: ns.pid,
+            batch: flags["batch"],
+            index: flags["index"],
+            action: "grow",
+            target: target,
+            landed: Date.now(),
+            result: result,
+        });
+
+        // a full port would push out reports that weren't read yet, so wait
+        // for room instead. give up after a while in case the governor is
+        // gone.
+        for (let attempt = 0; attempt < 100; attempt++) {
+            if (ns.tryWritePort(flags["port"], report)) {
+                break;
+            }
+
+            await ns.sleep(100);
+        }
     }
-    
-    await ns.grow(flags["_"][0]);
 }
@@ -0,0 +1,112 @@
+use std::fmt::Write as _;
+
+/// How much control we have over a machine, which picks its colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeStatus {
+    Owned,
+    Backdoored,
+    Rooted,
+    Locked,
+}
+
+impl NodeStatus {
+    fn get_colour(&self) -> &'static str {
+        use NodeStatus::*;
+
+        match self {
+            Owned => "lightblue",
+            Backdoored => "gold",
+            Rooted => "palegreen",
+            Locked => "lightgray",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DotNode {
+    pub hostname: String,
+    pub status: NodeStatus,
+    pub max_money: u64,
+}
+
+impl DotNode {
+    /// Width of the node in inches, growing with each order of magnitude of
+    /// money.
+    fn get_width(&self) -> f64 {
+        0.75 + (self.max_money as f64 + 1.).log10() / 4.
+    }
+}
+
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Writes an undirected graph in the Graphviz DOT language. Edges whose ends
+/// aren't both among the nodes are left out.
+pub fn render_dot(
+    nodes: &[DotNode],
+    edges: &[(String, String)],
+) -> String {
+    let mut retval = "graph network {\n".to_owned();
+    retval += "    node [shape=ellipse, style=filled];\n";
+
+    for node in nodes.iter() {
+        writeln!(
+            &mut retval,
+            "    {} [fillcolor={}, width={:.2}];",
+            quote(&node.hostname),
+            node.status.get_colour(),
+            node.get_width(),
+        )
+        .unwrap();
+    }
+
+    let is_node = |hostname: &str| nodes.iter().any(|n| n.hostname == hostname);
+
+    for (a, b) in edges.iter() {
+        if is_node(a) && is_node(b) {
+            writeln!(&mut retval, "    {} -- {};", quote(a), quote(b)).unwrap();
+        }
+    }
+
+    retval += "}\n";
+    retval
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        render_dot,
+        DotNode,
+        NodeStatus,
+    };
+
+    #[test]
+    fn renders_nodes_and_edges() {
+        let node = |hostname: &str, status, max_money| DotNode {
+            hostname: hostname.to_owned(),
+            status,
+            max_money,
+        };
+        let nodes = [
+            node("home", NodeStatus::Owned, 0),
+            node("n00dles", NodeStatus::Rooted, 1_750_000),
+            node("CSEC", NodeStatus::Backdoored, 0),
+        ];
+        let edges = [
+            ("home".to_owned(), "n00dles".to_owned()),
+            ("n00dles".to_owned(), "CSEC".to_owned()),
+            ("n00dles".to_owned(), "filtered-out".to_owned()),
+        ];
+
+        let dot = render_dot(&nodes, &edges);
+
+        assert!(dot.starts_with("graph network {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("\"home\" [fillcolor=lightblue, width=0.75];"));
+        assert!(dot.contains("\"n00dles\" [fillcolor=palegreen, width=2.31];"));
+        assert!(dot.contains("\"home\" -- \"n00dles\";"));
+        assert!(dot.contains("\"n00dles\" -- \"CSEC\";"));
+        assert!(!dot.contains("filtered-out"));
+    }
+}
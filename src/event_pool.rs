@@ -1,40 +1,288 @@
-use core::cmp::Ordering;
-use std::collections::BinaryHeap;
+use core::{
+    cmp::Ordering,
+    fmt::{
+        Display,
+        Formatter,
+        Result as FmtResult,
+    },
+};
+use std::collections::{
+    BTreeMap,
+    BinaryHeap,
+};
 
-use crate::netscript::{
-    Date,
-    NsWrapper,
+use crate::{
+    netscript::{
+        performance_now,
+        Date,
+        NsWrapper,
+    },
+    time_consts::SECOND,
 };
 
 pub trait Event {
     fn trigger_time(&self) -> f64;
     fn grace_period(&self) -> f64;
+
+    /// Tie-breaker for events sharing the same trigger time.
+    ///
+    /// Events with a higher priority are processed first. Events with equal
+    /// trigger times and priorities are processed in the order they were
+    /// added.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// Name under which the metrics of this event are grouped.
+    fn kind(&self) -> &'static str {
+        "event"
+    }
+}
+
+/// When a periodic event should stop being re-armed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeriodicStop {
+    /// Keep re-arming the event until the loop ends.
+    Never,
+    /// Stop once this many slots have gone by. Slots skipped because the
+    /// loop was running late count too.
+    AfterOccurrences(u64),
+    /// Stop once the next trigger time would be at or after this time.
+    At(f64),
+}
+
+/// A recipe for an event that is re-armed by the event loop every time it is
+/// handled, until its stop condition is met.
+///
+/// Trigger times are always computed as `start_time + n * interval` (plus
+/// jitter) rather than relative to the time the previous occurrence was
+/// handled, so a late handler does not push every following occurrence back.
+/// Occurrences that are already in the past by the time the event is re-armed
+/// are skipped.
+pub struct Periodic<E>
+where
+    E: Event,
+{
+    start_time: f64,
+    interval: f64,
+    jitter: f64,
+    stop: PeriodicStop,
+
+    occurrence: u64,
+    rng_state: u64,
+    make_event: Box<dyn FnMut(f64) -> E>,
+}
+
+impl<E> Periodic<E>
+where
+    E: Event,
+{
+    /// Creates a periodic event first triggering at `start_time`.
+    ///
+    /// `make_event` is given the trigger time of each occurrence and has to
+    /// return an event that triggers at that time.
+    pub fn new(
+        start_time: f64,
+        interval: f64,
+        make_event: impl FnMut(f64) -> E + 'static,
+    ) -> Periodic<E> {
+        Periodic {
+            start_time,
+            interval,
+            jitter: 0.,
+            stop: PeriodicStop::Never,
+
+            occurrence: 0,
+            rng_state: start_time.to_bits() ^
+                interval.to_bits().rotate_left(32),
+            make_event: Box::new(make_event),
+        }
+    }
+
+    /// Delays each occurrence by a random amount within `[0, jitter)`.
+    ///
+    /// The jitter is never accumulated; it only shifts each occurrence away
+    /// from its slot.
+    pub fn with_jitter(
+        mut self,
+        jitter: f64,
+    ) -> Periodic<E> {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_stop(
+        mut self,
+        stop: PeriodicStop,
+    ) -> Periodic<E> {
+        self.stop = stop;
+        self
+    }
+
+    fn is_valid(&self) -> bool {
+        self.start_time.is_finite() &&
+            self.interval.is_finite() &&
+            0. < self.interval &&
+            self.jitter.is_finite() &&
+            0. <= self.jitter
+    }
+
+    /// Returns a pseudorandom number in `[0, 1)`.
+    fn next_random(&mut self) -> f64 {
+        // splitmix64
+        self.rng_state = self.rng_state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns the trigger time of the current occurrence, or `None` if the
+    /// stop condition has been reached.
+    fn current_trigger_time(&mut self) -> Option<f64> {
+        let slot = self.start_time + self.interval * self.occurrence as f64;
+
+        let stopped = match self.stop {
+            PeriodicStop::Never => false,
+            PeriodicStop::AfterOccurrences(count) => count <= self.occurrence,
+            PeriodicStop::At(time) => time <= slot,
+        };
+
+        if stopped {
+            return None;
+        }
+
+        Some(slot + self.jitter * self.next_random())
+    }
+
+    /// Moves on to the earliest occurrence whose slot is not before `now`.
+    fn advance(
+        &mut self,
+        now: f64,
+    ) {
+        let elapsed_slots = ((now - self.start_time) / self.interval).ceil();
+        let elapsed_slots = elapsed_slots.max(0.) as u64;
+
+        self.occurrence = (self.occurrence + 1).max(elapsed_slots);
+    }
+}
+
+impl<E> core::fmt::Debug for Periodic<E>
+where
+    E: Event,
+{
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        f.debug_struct("Periodic")
+            .field("start_time", &self.start_time)
+            .field("interval", &self.interval)
+            .field("jitter", &self.jitter)
+            .field("stop", &self.stop)
+            .field("occurrence", &self.occurrence)
+            .finish_non_exhaustive()
+    }
 }
 
 pub struct EventLoopContext<E>
 where
     E: Event,
 {
-    next_events: Vec<E>,
+    next_events: Vec<EventWrapper<E>>,
+    next_sequence: u64,
+    metrics: EventLoopMetrics,
 }
 
 impl<E> EventLoopContext<E>
 where
     E: Event,
 {
+    fn new() -> EventLoopContext<E> {
+        EventLoopContext {
+            next_events: Vec::with_capacity(16),
+            next_sequence: 0,
+            metrics: EventLoopMetrics::default(),
+        }
+    }
+
+    /// Timing statistics of all the events handled so far.
+    pub fn metrics(&self) -> &EventLoopMetrics {
+        &self.metrics
+    }
+
+    /// Schedules an event.
+    ///
+    /// Returns the event back if its trigger time is NaN since it cannot be
+    /// ordered against the other events.
     pub fn add_event(
         &mut self,
         event: E,
+    ) -> Result<(), E> {
+        if event.trigger_time().is_nan() {
+            return Err(event);
+        }
+
+        self.push_event(event, None);
+        Ok(())
+    }
+
+    /// Schedules an event that is re-armed by the loop every time it gets
+    /// handled, until its stop condition is met.
+    ///
+    /// Returns the periodic event back if its timings are not finite or its
+    /// interval is not positive.
+    pub fn add_periodic_event(
+        &mut self,
+        mut periodic: Periodic<E>,
+    ) -> Result<(), Periodic<E>> {
+        if !periodic.is_valid() {
+            return Err(periodic);
+        }
+
+        if let Some(trigger_time) = periodic.current_trigger_time() {
+            let event = (periodic.make_event)(trigger_time);
+            self.push_event(event, Some(periodic));
+        }
+
+        Ok(())
+    }
+
+    fn rearm(
+        &mut self,
+        mut periodic: Periodic<E>,
+        now: f64,
+    ) {
+        periodic.advance(now);
+
+        if let Some(trigger_time) = periodic.current_trigger_time() {
+            let event = (periodic.make_event)(trigger_time);
+            self.push_event(event, Some(periodic));
+        }
+    }
+
+    fn push_event(
+        &mut self,
+        event: E,
+        periodic: Option<Periodic<E>>,
     ) {
-        self.next_events.push(event);
+        let priority = event.priority();
+        self.next_events.push(EventWrapper {
+            event,
+            priority,
+            sequence: self.next_sequence,
+            periodic,
+        });
+        self.next_sequence += 1;
     }
 
     fn drain_to_event_pool(
         &mut self,
         extensible: &mut impl Extend<EventWrapper<E>>,
     ) {
-        let drain_src = self.next_events.drain(..).map(|e| EventWrapper(e));
-        extensible.extend(drain_src)
+        extensible.extend(self.next_events.drain(..))
     }
 }
 
@@ -65,13 +313,258 @@ pub trait EventLoopState {
         &self,
         ns: &NsWrapper<'a>,
         event_heap: &BinaryHeap<EventWrapper<Self::Event>>,
+        metrics: &EventLoopMetrics,
     );
+
+    /// Called once the loop received a shutdown signal. No more events are
+    /// handled afterwards.
+    fn on_shutdown<'a>(
+        &mut self,
+        _ns: &NsWrapper<'a>,
+        _event_heap: &BinaryHeap<EventWrapper<Self::Event>>,
+    ) {
+    }
+}
+
+/// The message that has to be written to a port to shut down an event loop
+/// listening to it.
+pub const SHUTDOWN_MESSAGE: &str = "shutdown";
+
+/// The longest time, in milliseconds, that the event loop sleeps before
+/// checking its shutdown signals again.
+const SHUTDOWN_POLL_INTERVAL: f64 = SECOND;
+
+/// A way to ask a running event loop to stop.
+#[derive(Debug, Clone)]
+pub enum ShutdownSignal {
+    /// Shut down once a file with this name exists on the host running the
+    /// loop. The file is removed upon shutdown.
+    FlagFile(String),
+    /// Shut down once `SHUTDOWN_MESSAGE` is written to this port. The port is
+    /// dedicated to the signal, so anything else written to it is discarded.
+    Port(usize),
+}
+
+impl ShutdownSignal {
+    /// Returns true if the signal was received, removing it so it does not
+    /// get received again.
+    fn consume(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) -> bool {
+        use ShutdownSignal::*;
+
+        match self {
+            FlagFile(filename) => {
+                let hostname = ns.get_hostname();
+                let exists = ns.file_exists(filename, &hostname);
+
+                if exists {
+                    ns.rm(filename, Some(&hostname));
+                }
+
+                exists
+            },
+
+            Port(port) => {
+                // drain the whole port so that a stray message in front of
+                // the signal can't hide it
+                let mut is_shutdown = false;
+                while let Some(data) = ns.read_port(*port) {
+                    is_shutdown |=
+                        data.as_string().as_deref() == Some(SHUTDOWN_MESSAGE);
+                }
+
+                is_shutdown
+            },
+        }
+    }
+}
+
+/// Upper bounds, in milliseconds, of the buckets of a `LatencyHistogram`.
+/// Anything above the last bound falls into an extra overflow bucket.
+const LATENCY_BUCKET_BOUNDS: [f64; 10] =
+    [1., 2., 5., 10., 20., 50., 100., 200., 500., 1000.];
+
+/// A histogram of durations in milliseconds.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    buckets: [u64; LATENCY_BUCKET_BOUNDS.len() + 1],
+    count: u64,
+    sum: f64,
+    max: f64,
+}
+
+impl LatencyHistogram {
+    /// Records a duration. Negative durations are counted as zero.
+    pub fn record(
+        &mut self,
+        millis: f64,
+    ) {
+        let millis = millis.max(0.);
+        let bucket = LATENCY_BUCKET_BOUNDS
+            .iter()
+            .position(|bound| millis < *bound)
+            .unwrap_or(LATENCY_BUCKET_BOUNDS.len());
+
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum += millis;
+        self.max = self.max.max(millis);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        match self.count {
+            0 => 0.,
+            count => self.sum / count as f64,
+        }
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Returns the upper bound of the bucket containing the given quantile.
+    ///
+    /// Values in the overflow bucket are reported as the maximum recorded
+    /// value.
+    pub fn quantile(
+        &self,
+        quantile: f64,
+    ) -> f64 {
+        if self.count == 0 {
+            return 0.;
+        }
+
+        let rank = (quantile.clamp(0., 1.) * self.count as f64).ceil().max(1.);
+        let mut seen = 0;
+
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+
+            if rank <= seen as f64 {
+                return LATENCY_BUCKET_BOUNDS
+                    .get(bucket)
+                    .map(|bound| bound.min(self.max))
+                    .unwrap_or(self.max);
+            }
+        }
+
+        self.max
+    }
+}
+
+/// Timing statistics of a single kind of event.
+#[derive(Debug, Clone, Default)]
+pub struct EventKindMetrics {
+    /// How late the events were handled relative to their trigger time.
+    pub lateness: LatencyHistogram,
+    /// How long the handlers took to execute.
+    pub execution: LatencyHistogram,
+    /// How many events were handled past their grace period.
+    pub failures: u64,
+}
+
+/// Timing statistics of the event loop, grouped by `Event::kind`.
+#[derive(Debug, Clone, Default)]
+pub struct EventLoopMetrics {
+    by_kind: BTreeMap<&'static str, EventKindMetrics>,
+}
+
+impl EventLoopMetrics {
+    fn record(
+        &mut self,
+        kind: &'static str,
+        lateness: f64,
+        execution: f64,
+        failed: bool,
+    ) {
+        let metrics = self.by_kind.entry(kind).or_default();
+
+        metrics.lateness.record(lateness);
+        metrics.execution.record(execution);
+
+        if failed {
+            metrics.failures += 1;
+        }
+    }
+
+    pub fn iter(
+        &self
+    ) -> impl Iterator<Item = (&'static str, &EventKindMetrics)> {
+        self.by_kind.iter().map(|(kind, metrics)| (*kind, metrics))
+    }
 }
 
+impl Display for EventLoopMetrics {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> FmtResult {
+        writeln!(
+            f,
+            "| {:<16} | {:>8} | {:>6} | {:>9} | {:>9} | {:>9} | {:>9} | \
+             {:>9} | {:>9} |",
+            "event",
+            "count",
+            "fails",
+            "late avg",
+            "late p50",
+            "late p99",
+            "late max",
+            "exec avg",
+            "exec max",
+        )?;
+
+        for (kind, metrics) in self.iter() {
+            writeln!(
+                f,
+                "| {:<16} | {:>8} | {:>6} | {:>7.1}ms | {:>7.1}ms | \
+                 {:>7.1}ms | {:>7.1}ms | {:>7.2}ms | {:>7.2}ms |",
+                kind,
+                metrics.lateness.count(),
+                metrics.failures,
+                metrics.lateness.mean(),
+                metrics.lateness.quantile(0.5),
+                metrics.lateness.quantile(0.99),
+                metrics.lateness.max(),
+                metrics.execution.mean(),
+                metrics.execution.max(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An event as it sits in the event pool.
+///
+/// The priority is cached upon insertion and the sequence number is the
+/// insertion order, so that the ordering of two events never changes while
+/// they are inside the pool.
 #[derive(Debug)]
-pub struct EventWrapper<E>(pub E)
+pub struct EventWrapper<E>
 where
-    E: Event;
+    E: Event,
+{
+    event: E,
+    priority: i32,
+    sequence: u64,
+    periodic: Option<Periodic<E>>,
+}
+
+impl<E> EventWrapper<E>
+where
+    E: Event,
+{
+    fn into_parts(self) -> (E, Option<Periodic<E>>) {
+        (self.event, self.periodic)
+    }
+}
 
 impl<E> PartialOrd for EventWrapper<E>
 where
@@ -81,13 +574,7 @@ where
         &self,
         other: &Self,
     ) -> Option<Ordering> {
-        // since the std BinaryHeap is a max-heap which will remove the highest
-        // element first, we're reversing the comparison operator for this so
-        // the lowest element gets popped first
-        self.0
-            .trigger_time()
-            .partial_cmp(&other.0.trigger_time())
-            .map(|o| o.reverse())
+        Some(self.cmp(other))
     }
 }
 
@@ -99,7 +586,16 @@ where
         &self,
         other: &Self,
     ) -> Ordering {
-        self.partial_cmp(&other).unwrap()
+        // since the std BinaryHeap is a max-heap which will remove the highest
+        // element first, the "greatest" event is the one that has to be
+        // processed first: earliest trigger time, then highest priority, then
+        // earliest insertion.
+        other
+            .event
+            .trigger_time()
+            .total_cmp(&self.event.trigger_time())
+            .then(self.priority.cmp(&other.priority))
+            .then(other.sequence.cmp(&self.sequence))
     }
 }
 
@@ -111,21 +607,12 @@ where
         &self,
         other: &Self,
     ) -> bool {
-        self.0.trigger_time() == other.0.trigger_time()
+        self.cmp(other) == Ordering::Equal
     }
 }
 
 impl<E> Eq for EventWrapper<E> where E: Event {}
 
-impl<E> From<E> for EventWrapper<E>
-where
-    E: Event,
-{
-    fn from(e: E) -> EventWrapper<E> {
-        EventWrapper(e)
-    }
-}
-
 #[derive(Debug)]
 pub struct EventLoop<E>
 where
@@ -133,6 +620,7 @@ where
 {
     event_pool: BinaryHeap<EventWrapper<E::Event>>,
     state: E,
+    shutdown_signals: Vec<ShutdownSignal>,
 }
 
 impl<E> EventLoop<E>
@@ -143,50 +631,310 @@ where
         EventLoop {
             event_pool: BinaryHeap::new(),
             state,
+            shutdown_signals: vec![],
+        }
+    }
+
+    /// Makes the loop stop once the given signal is received.
+    pub fn add_shutdown_signal(
+        &mut self,
+        signal: ShutdownSignal,
+    ) {
+        self.shutdown_signals.push(signal);
+    }
+
+    /// Checks all the shutdown signals, consuming the ones that were
+    /// received.
+    fn is_shutdown_requested(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) -> bool {
+        let mut requested = false;
+
+        for signal in self.shutdown_signals.iter() {
+            requested |= signal.consume(ns);
         }
+
+        requested
     }
 
     pub async fn run(
         &mut self,
         ns: &NsWrapper<'_>,
     ) {
-        let mut context = EventLoopContext {
-            next_events: Vec::with_capacity(16),
-        };
+        let mut context = EventLoopContext::new();
+
+        // signals left over from a previous run are not meant for us
+        self.is_shutdown_requested(ns);
 
         // populate the pool first
         self.state.initial_run(ns, &mut context);
         context.drain_to_event_pool(&mut self.event_pool);
 
-        let mut last_slept_until = Date::now();
         while let Some(event) = self.event_pool.pop() {
-            let event = event.0;
-            let grace_period = event.grace_period();
+            let trigger_time = event.event.trigger_time();
 
-            // TODO: rework this.
+            // if the trigger time is greater than now, sleep until then. the
+            // sleep is split up so that shutdown requests get noticed early.
+            let mut now = Date::now();
+            loop {
+                if self.is_shutdown_requested(ns) {
+                    self.event_pool.push(event);
+                    self.state.on_shutdown(ns, &self.event_pool);
 
-            // if the trigger time is greater than now, sleep then execute
-            if last_slept_until <= event.trigger_time() {
-                ns.sleep((event.trigger_time() - last_slept_until).round() as i32)
-                    .await;
-                last_slept_until = event.trigger_time();
+                    return;
+                }
 
-                self.state.on_event(ns, event, &mut context);
+                if trigger_time <= now {
+                    break;
+                }
+
+                let nap = (trigger_time - now).min(SHUTDOWN_POLL_INTERVAL);
+                ns.sleep(nap.ceil() as i32).await;
+                now = Date::now();
             }
 
-            // if the trigger time is between now and grace period, execute
-            else if last_slept_until - grace_period <= event.trigger_time() {
+            let (event, periodic) = event.into_parts();
+            let grace_period = event.grace_period();
+            let kind = event.kind();
+
+            // if the trigger time is within the grace period, execute.
+            // otherwise, it's too late. fail.
+            let lateness = now - trigger_time;
+            let failed = grace_period < lateness;
+
+            let handler_start = performance_now();
+            if failed {
+                self.state.on_event_fail(ns, event, &mut context);
+            }
+            else {
                 self.state.on_event(ns, event, &mut context);
             }
+            let execution = performance_now() - handler_start;
 
-            // if the trigger time is beyond grace period, it's too late. fail.
-            else {
-                self.state.on_event_fail(ns, event, &mut context);
+            context.metrics.record(kind, lateness, execution, failed);
+
+            if let Some(periodic) = periodic {
+                context.rearm(periodic, Date::now());
             }
 
             context.drain_to_event_pool(&mut self.event_pool);
 
-            self.state.post_loop_inspect(ns, &self.event_pool);
+            self.state.post_loop_inspect(
+                ns,
+                &self.event_pool,
+                &context.metrics,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BinaryHeap;
+
+    use super::{
+        Event,
+        EventLoopContext,
+        EventLoopMetrics,
+        LatencyHistogram,
+        Periodic,
+        PeriodicStop,
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct TestEvent {
+        name: &'static str,
+        trigger_time: f64,
+        priority: i32,
+    }
+
+    impl Event for TestEvent {
+        fn trigger_time(&self) -> f64 {
+            self.trigger_time
+        }
+
+        fn grace_period(&self) -> f64 {
+            0.
         }
+
+        fn priority(&self) -> i32 {
+            self.priority
+        }
+    }
+
+    fn pop_order(events: Vec<(&'static str, f64, i32)>) -> Vec<&'static str> {
+        let mut ctx = EventLoopContext::new();
+        for (name, trigger_time, priority) in events {
+            ctx.add_event(TestEvent {
+                name,
+                trigger_time,
+                priority,
+            })
+            .unwrap();
+        }
+
+        let mut heap = BinaryHeap::new();
+        ctx.drain_to_event_pool(&mut heap);
+
+        core::iter::from_fn(|| heap.pop())
+            .map(|e| e.event.name)
+            .collect()
+    }
+
+    #[test]
+    fn earliest_trigger_time_first() {
+        let order =
+            pop_order(vec![("c", 30., 0), ("a", 10., 0), ("b", 20., 0)]);
+        assert_eq!(order, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn higher_priority_first_on_same_time() {
+        let order = pop_order(vec![
+            ("hack", 10., 0),
+            ("weaken", 10., 1),
+            ("x", 5., -1),
+        ]);
+        assert_eq!(order, ["x", "weaken", "hack"]);
+    }
+
+    #[test]
+    fn insertion_order_on_full_tie() {
+        let order = pop_order(vec![
+            ("first", 10., 0),
+            ("second", 10., 0),
+            ("third", 10., 0),
+            ("fourth", 10., 0),
+            ("fifth", 10., 0),
+        ]);
+        assert_eq!(order, ["first", "second", "third", "fourth", "fifth"]);
+    }
+
+    #[test]
+    fn nan_is_rejected() {
+        let mut ctx = EventLoopContext::new();
+        let rejected = ctx.add_event(TestEvent {
+            name: "nan",
+            trigger_time: f64::NAN,
+            priority: 0,
+        });
+
+        assert_eq!(rejected.unwrap_err().name, "nan");
+        assert!(ctx.next_events.is_empty());
+    }
+
+    fn periodic(
+        start_time: f64,
+        interval: f64,
+    ) -> Periodic<TestEvent> {
+        Periodic::new(start_time, interval, |trigger_time| TestEvent {
+            name: "periodic",
+            trigger_time,
+            priority: 0,
+        })
+    }
+
+    #[test]
+    fn periodic_does_not_drift() {
+        let mut periodic = periodic(1000., 100.);
+        let mut times = vec![periodic.current_trigger_time().unwrap()];
+
+        // handle every occurrence a bit late
+        for _ in 0..3 {
+            let late_now = *times.last().unwrap() + 30.;
+            periodic.advance(late_now);
+            times.push(periodic.current_trigger_time().unwrap());
+        }
+
+        assert_eq!(times, [1000., 1100., 1200., 1300.]);
+    }
+
+    #[test]
+    fn periodic_skips_missed_occurrences() {
+        let mut periodic = periodic(1000., 100.);
+        periodic.advance(1350.);
+
+        assert_eq!(periodic.current_trigger_time(), Some(1400.));
+    }
+
+    #[test]
+    fn periodic_jitter_stays_within_slot() {
+        let mut periodic = periodic(0., 100.).with_jitter(20.);
+
+        for occurrence in 0..100 {
+            let slot = occurrence as f64 * 100.;
+            let trigger_time = periodic.current_trigger_time().unwrap();
+
+            assert!(slot <= trigger_time && trigger_time < slot + 20.);
+            periodic.advance(slot);
+        }
+    }
+
+    #[test]
+    fn periodic_stops() {
+        let mut after_two =
+            periodic(0., 100.).with_stop(PeriodicStop::AfterOccurrences(2));
+        assert!(after_two.current_trigger_time().is_some());
+        after_two.advance(0.);
+        assert!(after_two.current_trigger_time().is_some());
+        after_two.advance(100.);
+        assert!(after_two.current_trigger_time().is_none());
+
+        let mut at_250 = periodic(0., 100.).with_stop(PeriodicStop::At(250.));
+        at_250.advance(190.);
+        assert_eq!(at_250.current_trigger_time(), Some(200.));
+        at_250.advance(200.);
+        assert!(at_250.current_trigger_time().is_none());
+    }
+
+    #[test]
+    fn periodic_rearms_through_context() {
+        let mut ctx = EventLoopContext::new();
+        ctx.add_periodic_event(periodic(1000., 100.)).unwrap();
+        assert!(ctx.add_periodic_event(periodic(1000., 0.)).is_err());
+
+        let (event, periodic) = ctx.next_events.pop().unwrap().into_parts();
+        assert_eq!(event.trigger_time, 1000.);
+
+        ctx.rearm(periodic.unwrap(), 1010.);
+        let wrapper = ctx.next_events.pop().unwrap();
+        assert_eq!(wrapper.event.trigger_time, 1100.);
+        assert_eq!(wrapper.sequence, 1);
+    }
+
+    #[test]
+    fn histogram_statistics() {
+        let mut histogram = LatencyHistogram::default();
+        for millis in [-3., 0.5, 0.5, 3., 4., 15., 1500.] {
+            histogram.record(millis);
+        }
+
+        assert_eq!(histogram.count(), 7);
+        assert_eq!(histogram.max(), 1500.);
+        assert_eq!(histogram.mean(), 1523. / 7.);
+        assert_eq!(histogram.quantile(0.5), 5.);
+        assert_eq!(histogram.quantile(0.), 1.);
+        assert_eq!(histogram.quantile(1.), 1500.);
+    }
+
+    #[test]
+    fn metrics_grouped_by_kind() {
+        let mut metrics = EventLoopMetrics::default();
+        metrics.record("poll", 3., 0.1, false);
+        metrics.record("poll", 80., 0.2, true);
+        metrics.record("free", 1., 0.1, false);
+
+        let kinds = metrics.iter().collect::<Vec<_>>();
+        assert_eq!(kinds.len(), 2);
+        assert_eq!(kinds[0].0, "free");
+        assert_eq!(kinds[0].1.failures, 0);
+        assert_eq!(kinds[1].0, "poll");
+        assert_eq!(kinds[1].1.lateness.count(), 2);
+        assert_eq!(kinds[1].1.failures, 1);
+
+        let summary = metrics.to_string();
+        assert_eq!(summary.lines().count(), 3);
+        assert!(summary.lines().nth(1).unwrap().starts_with("| free "));
     }
 }
@@ -0,0 +1,413 @@
+use std::{
+    cmp::Ordering,
+    str::FromStr,
+};
+
+use clap::Args;
+
+use crate::{
+    machine::Machine,
+    netscript::NsWrapper,
+};
+
+/// A column of the scan table that machines can be filtered and sorted on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Hostname,
+    IpAddress,
+    Organization,
+    Degree,
+    Money,
+    Skill,
+    Security,
+    Cores,
+    Ports,
+    Root,
+    Backdoor,
+    Owned,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueKind {
+    Number,
+    Text,
+    Bool,
+}
+
+impl Column {
+    fn kind(&self) -> ValueKind {
+        use Column::*;
+
+        match self {
+            Hostname | IpAddress | Organization => ValueKind::Text,
+            Degree | Money | Skill | Security | Cores | Ports => {
+                ValueKind::Number
+            },
+            Root | Backdoor | Owned => ValueKind::Bool,
+        }
+    }
+
+    pub fn get(
+        &self,
+        ns: &NsWrapper,
+        machine: &Machine,
+    ) -> ColumnValue {
+        use Column::*;
+        use ColumnValue::*;
+
+        match self {
+            Hostname => Text(machine.get_hostname().to_owned()),
+            IpAddress => Text(machine.get_ip_address().to_owned()),
+            Organization => Text(machine.get_organization_name().to_owned()),
+            Degree => Number(machine.get_degree() as f64),
+            Money => Number(machine.get_max_money() as f64),
+            Skill => Number(machine.get_min_hacking_skill() as f64),
+            Security => Number(machine.get_min_security()),
+            Cores => Number(machine.get_cpu_cores() as f64),
+            Ports => Number(machine.get_required_open_ports() as f64),
+            Root => Bool(machine.is_root(ns)),
+            Backdoor => Bool(machine.is_backdoored(ns)),
+            Owned => Bool(machine.is_player_owned()),
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Column, String> {
+        use Column::*;
+
+        match s.trim().to_lowercase().as_str() {
+            "hostname" | "name" => Ok(Hostname),
+            "ip" | "ip_address" => Ok(IpAddress),
+            "org" | "organization" => Ok(Organization),
+            "degree" | "depth" => Ok(Degree),
+            "money" | "max_money" => Ok(Money),
+            "skill" | "level" | "hacking_skill" => Ok(Skill),
+            "security" | "sec" | "min_security" => Ok(Security),
+            "cores" | "cpu_cores" => Ok(Cores),
+            "ports" | "required_open_ports" => Ok(Ports),
+            "root" => Ok(Root),
+            "backdoor" => Ok(Backdoor),
+            "owned" => Ok(Owned),
+            other => Err(format!("unknown column: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnValue {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+}
+
+impl ColumnValue {
+    fn parse(
+        kind: ValueKind,
+        s: &str,
+    ) -> Result<ColumnValue, String> {
+        match kind {
+            ValueKind::Number => s
+                .parse::<f64>()
+                .map(ColumnValue::Number)
+                .map_err(|_| format!("not a number: {}", s)),
+            ValueKind::Bool => s
+                .parse::<bool>()
+                .map(ColumnValue::Bool)
+                .map_err(|_| format!("not true or false: {}", s)),
+            ValueKind::Text => Ok(ColumnValue::Text(s.to_lowercase())),
+        }
+    }
+
+    /// Compares two values of the same column. Text is compared without
+    /// regard to case.
+    fn compare(
+        &self,
+        other: &ColumnValue,
+    ) -> Ordering {
+        use ColumnValue::*;
+
+        match (self, other) {
+            (Number(a), Number(b)) => a.total_cmp(b),
+            (Text(a), Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+            (Bool(a), Bool(b)) => a.cmp(b),
+            // values of a column are always of the same kind
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    /// The text contains the value.
+    Contains,
+}
+
+impl Operator {
+    /// Longer operators come first so that `<=` isn't taken for `<`.
+    const ALL: [(&'static str, Operator); 7] = [
+        ("<=", Operator::LessEqual),
+        (">=", Operator::GreaterEqual),
+        ("!=", Operator::NotEqual),
+        ("=", Operator::Equal),
+        ("<", Operator::Less),
+        (">", Operator::Greater),
+        ("~", Operator::Contains),
+    ];
+}
+
+/// A predicate on a column, like `ports<=3`, `root=false` or `org~Corp`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    column: Column,
+    operator: Operator,
+    value: ColumnValue,
+}
+
+impl Filter {
+    fn matches(
+        &self,
+        actual: &ColumnValue,
+    ) -> bool {
+        use Operator::*;
+
+        if let (Contains, ColumnValue::Text(actual), ColumnValue::Text(value)) =
+            (self.operator, actual, &self.value)
+        {
+            return actual.to_lowercase().contains(value.as_str());
+        }
+
+        let ordering = actual.compare(&self.value);
+
+        match self.operator {
+            Equal => ordering.is_eq(),
+            NotEqual => ordering.is_ne(),
+            Less => ordering.is_lt(),
+            LessEqual => ordering.is_le(),
+            Greater => ordering.is_gt(),
+            GreaterEqual => ordering.is_ge(),
+            Contains => false,
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Filter, String> {
+        let start = s
+            .find(['<', '>', '=', '!', '~'])
+            .ok_or_else(|| format!("no operator in filter: {}", s))?;
+
+        let (symbol, operator) = Operator::ALL
+            .iter()
+            .find(|(symbol, _)| s[start..].starts_with(symbol))
+            .ok_or_else(|| format!("unknown operator in filter: {}", s))?;
+
+        let column = s[..start].parse::<Column>()?;
+        let value = s[start + symbol.len()..].trim();
+
+        let is_allowed = match column.kind() {
+            ValueKind::Number => *operator != Operator::Contains,
+            ValueKind::Text => true,
+            ValueKind::Bool => {
+                matches!(operator, Operator::Equal | Operator::NotEqual)
+            },
+        };
+
+        if !is_allowed {
+            return Err(format!("{} cannot be used on {:?}", symbol, column));
+        }
+
+        Ok(Filter {
+            column,
+            operator: *operator,
+            value: ColumnValue::parse(column.kind(), value)?,
+        })
+    }
+}
+
+/// A column to sort on, like `money`, `money:desc` or `ports:asc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    column: Column,
+    descending: bool,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SortKey, String> {
+        let (column, direction) = s.split_once(':').unwrap_or((s, "asc"));
+
+        let descending = match direction.trim().to_lowercase().as_str() {
+            "asc" => false,
+            "desc" => true,
+            other => return Err(format!("unknown sort direction: {}", other)),
+        };
+
+        Ok(SortKey {
+            column: column.parse()?,
+            descending,
+        })
+    }
+}
+
+// which machines a mode works on, and in which order. this is not a doc
+// comment since clap would show it as the about of the scan subcommand.
+#[derive(Args, Debug, Clone, Default)]
+pub struct MachineSelection {
+    #[arg(
+        long = "filter",
+        value_name = "PREDICATE",
+        help = "only keep the machines matching this, e.g. ports<=3, \
+                money>1e9, org~Corp or root=false. can be repeated"
+    )]
+    filters: Vec<Filter>,
+    #[arg(
+        long = "sort",
+        value_name = "COLUMN[:asc|desc]",
+        help = "sort on this column. can be repeated to break ties"
+    )]
+    sort_keys: Vec<SortKey>,
+    #[arg(long, help = "only keep this many machines")]
+    limit: Option<usize>,
+}
+
+impl MachineSelection {
+    /// Filters, sorts then truncates the machines. The sort is stable, so
+    /// machines that compare equal keep their previous order.
+    pub fn apply<M>(
+        &self,
+        ns: &NsWrapper,
+        machines: Vec<M>,
+    ) -> Vec<M>
+    where
+        M: AsRef<Machine>,
+    {
+        let mut machines = machines
+            .into_iter()
+            .filter(|m| {
+                self.filters.iter().all(|filter| {
+                    filter.matches(&filter.column.get(ns, m.as_ref()))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if !self.sort_keys.is_empty() {
+            machines.sort_by_cached_key(|m| {
+                self.sort_keys
+                    .iter()
+                    .map(|key| SortValue {
+                        value: key.column.get(ns, m.as_ref()),
+                        descending: key.descending,
+                    })
+                    .collect::<Vec<_>>()
+            });
+        }
+
+        if let Some(limit) = self.limit {
+            machines.truncate(limit);
+        }
+
+        machines
+    }
+}
+
+/// A value to sort on, ordered according to its sort direction.
+#[derive(Debug, Clone, PartialEq)]
+struct SortValue {
+    value: ColumnValue,
+    descending: bool,
+}
+
+impl Eq for SortValue {}
+
+impl PartialOrd for SortValue {
+    fn partial_cmp(
+        &self,
+        other: &Self,
+    ) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortValue {
+    fn cmp(
+        &self,
+        other: &Self,
+    ) -> Ordering {
+        let ordering = self.value.compare(&other.value);
+
+        match self.descending {
+            true => ordering.reverse(),
+            false => ordering,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Column,
+        ColumnValue,
+        Filter,
+        Operator,
+        SortKey,
+    };
+
+    #[test]
+    fn parse_filters() {
+        let filter = "ports<=3".parse::<Filter>().unwrap();
+        assert_eq!(filter.column, Column::Ports);
+        assert_eq!(filter.operator, Operator::LessEqual);
+        assert_eq!(filter.value, ColumnValue::Number(3.));
+
+        let filter = "money>1e9".parse::<Filter>().unwrap();
+        assert_eq!(filter.value, ColumnValue::Number(1e9));
+
+        let filter = "root=false".parse::<Filter>().unwrap();
+        assert_eq!(filter.value, ColumnValue::Bool(false));
+
+        assert!("root<true".parse::<Filter>().is_err());
+        assert!("money~1".parse::<Filter>().is_err());
+        assert!("ports<=three".parse::<Filter>().is_err());
+        assert!("colour=red".parse::<Filter>().is_err());
+        assert!("ports".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn filters_match() {
+        let filter = "org~corp".parse::<Filter>().unwrap();
+        assert!(filter.matches(&ColumnValue::Text("MegaCorp".to_owned())));
+        assert!(!filter.matches(&ColumnValue::Text("Netlink".to_owned())));
+
+        let filter = "ports<=3".parse::<Filter>().unwrap();
+        assert!(filter.matches(&ColumnValue::Number(3.)));
+        assert!(!filter.matches(&ColumnValue::Number(4.)));
+
+        let filter = "owned!=true".parse::<Filter>().unwrap();
+        assert!(filter.matches(&ColumnValue::Bool(false)));
+    }
+
+    #[test]
+    fn parse_sort_keys() {
+        let key = "money:desc".parse::<SortKey>().unwrap();
+        assert_eq!(key.column, Column::Money);
+        assert!(key.descending);
+
+        let key = "level".parse::<SortKey>().unwrap();
+        assert_eq!(key.column, Column::Skill);
+        assert!(!key.descending);
+
+        assert!("money:up".parse::<SortKey>().is_err());
+    }
+}
@@ -0,0 +1,423 @@
+use std::fmt::Write as _;
+
+use clap::Args;
+use wasm_bindgen::JsValue;
+
+use crate::{
+    netscript::NsWrapper,
+    utils::get_attribute,
+};
+
+/// Money a hash is worth when sold through "Sell for Money".
+const MONEY_PER_HASH: f64 = 1e6 / 4.;
+
+/// Upper bound of purchases in one run, in case the costs never catch up.
+const MAX_PURCHASES: usize = 1000;
+
+/// Upper bound of hash upgrades bought in one run.
+const MAX_HASH_SPENDS: usize = 1000;
+
+#[derive(Args, Debug, Clone)]
+pub struct HacknetMode {
+    #[arg(
+        long,
+        default_value_t = 3600.,
+        help = "only buy what pays for itself within this many seconds"
+    )]
+    horizon: f64,
+    #[arg(
+        long,
+        default_value_t = 0.,
+        help = "money on home that is never spent on hacknet"
+    )]
+    reserve: f64,
+    #[arg(
+        long = "spend-hashes",
+        value_name = "UPGRADE",
+        help = "spend the hashes on this upgrade, e.g. \"Sell for Money\". \
+                can be repeated, earlier ones are bought first"
+    )]
+    hash_upgrades: Vec<String>,
+    #[arg(
+        long,
+        value_name = "HOSTNAME",
+        help = "server targeted by the hash upgrades that need one"
+    )]
+    hash_target: Option<String>,
+}
+
+impl HacknetMode {
+    pub fn execute(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) {
+        let mut print_str = "\n".to_owned();
+
+        self.buy_upgrades(ns, &mut print_str);
+        self.spend_hashes(ns, &mut print_str);
+
+        if print_str == "\n" {
+            ns.tprint("Nothing was bought.");
+        }
+        else {
+            ns.tprint(&print_str);
+        }
+    }
+
+    /// Keeps buying whatever pays for itself the fastest, until nothing does
+    /// within the horizon or we run out of money.
+    fn buy_upgrades(
+        &self,
+        ns: &NsWrapper<'_>,
+        print_str: &mut String,
+    ) {
+        for _ in 0..MAX_PURCHASES {
+            let nodes = (0..ns.hacknet_num_nodes())
+                .map(|index| NodeStats::get(ns, index))
+                .collect::<Vec<_>>();
+
+            let money = ns.get_server_money_available("home").unwrap() as f64;
+            let budget = money - self.reserve;
+
+            let upgrades = get_upgrades(ns, &nodes);
+            let Some(best) = get_best_upgrade(&upgrades, budget, self.horizon)
+            else {
+                break;
+            };
+
+            if !best.kind.buy(ns) {
+                break;
+            }
+
+            writeln!(
+                print_str,
+                "{} for {:.3e}, pays back in {:.0}s",
+                best.kind,
+                best.cost,
+                best.get_payback_time()
+            )
+            .unwrap();
+        }
+    }
+
+    /// Spends the hashes on the configured upgrades, in order.
+    fn spend_hashes(
+        &self,
+        ns: &NsWrapper<'_>,
+        print_str: &mut String,
+    ) {
+        let target = self.hash_target.as_deref();
+
+        for upgrade in self.hash_upgrades.iter() {
+            let mut bought = 0;
+
+            while bought < MAX_HASH_SPENDS {
+                let cost = match ns.hacknet_hash_cost(upgrade) {
+                    Ok(cost) => cost,
+                    Err(_) => {
+                        writeln!(print_str, "unknown hash upgrade: {}", upgrade)
+                            .unwrap();
+                        break;
+                    },
+                };
+
+                if ns.hacknet_num_hashes() < cost {
+                    break;
+                }
+
+                match ns.hacknet_spend_hashes(upgrade, target) {
+                    Ok(true) => bought += 1,
+                    Ok(false) => break,
+                    Err(_) => {
+                        writeln!(
+                            print_str,
+                            "{} needs a valid --hash-target",
+                            upgrade
+                        )
+                        .unwrap();
+                        break;
+                    },
+                }
+            }
+
+            if 0 < bought {
+                writeln!(print_str, "{}: bought {} time(s)", upgrade, bought)
+                    .unwrap();
+            }
+        }
+    }
+}
+
+/// Returns the production of a node or a server before multipliers, in money
+/// per second for nodes and hashes per second for servers.
+fn get_base_production(
+    is_server: bool,
+    level: f64,
+    ram: f64,
+    cores: f64,
+) -> f64 {
+    match is_server {
+        false => level * 1.5 * 1.035f64.powf(ram - 1.) * (cores + 5.) / 6.,
+        true => {
+            level * 0.001 * 1.07f64.powf(ram.log2()) * (1. + (cores - 1.) / 5.)
+        },
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NodeStats {
+    level: f64,
+    ram: f64,
+    cores: f64,
+    production: f64,
+    /// Hacknet servers produce hashes instead of money.
+    is_server: bool,
+}
+
+impl NodeStats {
+    fn get(
+        ns: &NsWrapper<'_>,
+        index: usize,
+    ) -> NodeStats {
+        let stats = ns.hacknet_get_node_stats(index);
+        let get = |field| {
+            get_attribute(&stats, field, JsValue::as_f64)
+                .unwrap()
+                .unwrap()
+        };
+
+        NodeStats {
+            level: get("level"),
+            ram: get("ram"),
+            cores: get("cores"),
+            production: get("production"),
+            is_server: get_attribute(&stats, "hashCapacity", JsValue::as_f64)
+                .unwrap()
+                .is_some(),
+        }
+    }
+
+    /// Stats of the node a purchase gets us. It has the multipliers of the
+    /// nodes we already have, or no multiplier at all for the first node.
+    fn get_purchase_template(nodes: &[NodeStats]) -> NodeStats {
+        nodes.first().copied().unwrap_or(NodeStats {
+            level: 1.,
+            ram: 1.,
+            cores: 1.,
+            production: get_base_production(false, 1., 1., 1.),
+            is_server: false,
+        })
+    }
+
+    /// Our multipliers, as seen through the production of this node.
+    fn get_multiplier(&self) -> f64 {
+        let base = get_base_production(
+            self.is_server,
+            self.level,
+            self.ram,
+            self.cores,
+        );
+
+        match 0. < base {
+            true => self.production / base,
+            false => 1.,
+        }
+    }
+
+    /// Money per second this node would make with different stats.
+    fn get_money_rate(
+        &self,
+        level: f64,
+        ram: f64,
+        cores: f64,
+    ) -> f64 {
+        let production =
+            get_base_production(self.is_server, level, ram, cores) *
+                self.get_multiplier();
+
+        match self.is_server {
+            true => production * MONEY_PER_HASH,
+            false => production,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UpgradeKind {
+    Purchase,
+    Level(usize),
+    Ram(usize),
+    Core(usize),
+}
+
+impl UpgradeKind {
+    fn buy(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) -> bool {
+        use UpgradeKind::*;
+
+        match *self {
+            Purchase => ns.hacknet_purchase_node().is_some(),
+            Level(index) => ns.hacknet_upgrade_level(index, 1),
+            Ram(index) => ns.hacknet_upgrade_ram(index, 1),
+            Core(index) => ns.hacknet_upgrade_core(index, 1),
+        }
+    }
+}
+
+impl std::fmt::Display for UpgradeKind {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        use UpgradeKind::*;
+
+        match self {
+            Purchase => write!(f, "new node"),
+            Level(index) => write!(f, "node {}: level", index),
+            Ram(index) => write!(f, "node {}: ram", index),
+            Core(index) => write!(f, "node {}: core", index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Upgrade {
+    kind: UpgradeKind,
+    cost: f64,
+    /// Extra money per second once bought.
+    gain: f64,
+}
+
+impl Upgrade {
+    /// Seconds until the upgrade has made back its cost.
+    fn get_payback_time(&self) -> f64 {
+        match 0. < self.gain {
+            true => self.cost / self.gain,
+            false => f64::INFINITY,
+        }
+    }
+}
+
+/// Lists everything that can be bought, along with what it earns.
+fn get_upgrades(
+    ns: &NsWrapper<'_>,
+    nodes: &[NodeStats],
+) -> Vec<Upgrade> {
+    let mut upgrades = vec![];
+
+    if nodes.len() < ns.hacknet_max_num_nodes() {
+        let template = NodeStats::get_purchase_template(nodes);
+
+        upgrades.push(Upgrade {
+            kind: UpgradeKind::Purchase,
+            cost: ns.hacknet_get_purchase_node_cost(),
+            gain: template.get_money_rate(1., 1., 1.),
+        });
+    }
+
+    for (index, node) in nodes.iter().enumerate() {
+        let current = node.get_money_rate(node.level, node.ram, node.cores);
+        let candidates = [
+            (
+                UpgradeKind::Level(index),
+                ns.hacknet_get_level_upgrade_cost(index, 1),
+                node.get_money_rate(node.level + 1., node.ram, node.cores),
+            ),
+            (
+                UpgradeKind::Ram(index),
+                ns.hacknet_get_ram_upgrade_cost(index, 1),
+                node.get_money_rate(node.level, node.ram * 2., node.cores),
+            ),
+            (
+                UpgradeKind::Core(index),
+                ns.hacknet_get_core_upgrade_cost(index, 1),
+                node.get_money_rate(node.level, node.ram, node.cores + 1.),
+            ),
+        ];
+
+        upgrades.extend(
+            candidates
+                .into_iter()
+                // maxed out upgrades cost infinity
+                .filter(|(_, cost, _)| cost.is_finite())
+                .map(|(kind, cost, rate)| Upgrade {
+                    kind,
+                    cost,
+                    gain: rate - current,
+                }),
+        );
+    }
+
+    upgrades
+}
+
+/// Returns the affordable upgrade that pays for itself the fastest, if it
+/// does so within `horizon` seconds.
+fn get_best_upgrade(
+    upgrades: &[Upgrade],
+    budget: f64,
+    horizon: f64,
+) -> Option<&Upgrade> {
+    upgrades
+        .iter()
+        .filter(|upgrade| upgrade.get_payback_time() <= horizon)
+        .min_by(|a, b| a.get_payback_time().total_cmp(&b.get_payback_time()))
+        .filter(|upgrade| upgrade.cost <= budget)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        get_base_production,
+        get_best_upgrade,
+        NodeStats,
+        Upgrade,
+        UpgradeKind,
+    };
+
+    #[test]
+    fn base_production() {
+        assert_eq!(get_base_production(false, 1., 1., 1.), 1.5);
+        assert_eq!(get_base_production(true, 1., 1., 1.), 0.001);
+        assert!(
+            get_base_production(false, 10., 2., 1.) <
+                get_base_production(false, 10., 4., 1.)
+        );
+    }
+
+    #[test]
+    fn best_upgrade_pays_back_first() {
+        let upgrade = |kind, cost, gain| Upgrade { kind, cost, gain };
+        let upgrades = [
+            upgrade(UpgradeKind::Purchase, 1000., 1.),
+            upgrade(UpgradeKind::Level(0), 100., 1.),
+            upgrade(UpgradeKind::Ram(0), 300., 2.),
+            upgrade(UpgradeKind::Core(0), 50., 0.),
+        ];
+
+        let best = get_best_upgrade(&upgrades, 1e9, 3600.).unwrap();
+        assert_eq!(best.kind, UpgradeKind::Level(0));
+
+        // nothing pays back soon enough
+        assert_eq!(get_best_upgrade(&upgrades, 1e9, 10.), None);
+
+        // wait for the best upgrade instead of buying a worse one
+        assert_eq!(get_best_upgrade(&upgrades, 60., 3600.), None);
+    }
+
+    #[test]
+    fn first_node_is_worth_buying() {
+        let template = NodeStats::get_purchase_template(&[]);
+        assert_eq!(template.get_multiplier(), 1.);
+
+        let upgrade = Upgrade {
+            kind: UpgradeKind::Purchase,
+            cost: 1000.,
+            gain: template.get_money_rate(1., 1., 1.),
+        };
+        assert_eq!(upgrade.gain, 1.5);
+        assert!(upgrade.get_payback_time().is_finite());
+    }
+}
@@ -1,10 +1,20 @@
 mod autohack;
+mod dot;
+mod filter;
+mod hacknet;
 mod machine;
 mod netscript;
+mod port;
+mod port_opener;
+mod procs;
 mod scan;
 mod script_deploy;
+mod servers;
+mod stock;
 mod time_consts;
+mod tree;
 mod utils;
+mod watch;
 //mod contracts;
 mod event_pool;
 //mod range_set;
@@ -18,7 +28,14 @@ use js_sys::Array;
 // TODO: don't use glob unless necessary.
 use wasm_bindgen::prelude::*;
 
-use crate::scan::ScanMode;
+use crate::{
+    autohack::AutoHackMode,
+    hacknet::HacknetMode,
+    procs::ProcsMode,
+    scan::ScanMode,
+    servers::ServersMode,
+    stock::StockMode,
+};
 
 #[derive(Args)]
 struct HGWTarget {
@@ -30,7 +47,15 @@ enum AppMode {
     //#[clap(help = "scans the network")]
     Scan(ScanMode),
     //#[clap(help = "perform automated hacking on the network")]
-    AutoHack,
+    AutoHack(AutoHackMode),
+    //#[clap(help = "buy and upgrade servers")]
+    Servers(ServersMode),
+    //#[clap(help = "buy hacknet nodes and upgrades that pay back quickly")]
+    Hacknet(HacknetMode),
+    //#[clap(help = "trade stocks, with or without the 4S market data")]
+    Stock(StockMode),
+    //#[clap(help = "list or kill the scripts running on the network")]
+    Procs(ProcsMode),
     //#[clap(help = "automatically solve a contract")]
     Contract,
 }
@@ -55,9 +80,19 @@ pub async fn execute_command(
             ns.tprint(&*error_msg);
         },
 
-        Ok(AppMode::Scan(scan_mode)) => scan_mode.execute(&ns),
+        Ok(AppMode::Scan(scan_mode)) => scan_mode.execute(&ns).await,
+
+        Ok(AppMode::AutoHack(autohack_mode)) => {
+            autohack_mode.execute(&ns).await
+        },
+
+        Ok(AppMode::Servers(servers_mode)) => servers_mode.execute(&ns),
+
+        Ok(AppMode::Hacknet(hacknet_mode)) => hacknet_mode.execute(&ns),
+
+        Ok(AppMode::Stock(stock_mode)) => stock_mode.execute(&ns).await,
 
-        Ok(AppMode::AutoHack) => crate::autohack::auto_hack(&ns).await,
+        Ok(AppMode::Procs(procs_mode)) => procs_mode.execute(&ns),
 
         Ok(AppMode::Contract) => ns.tprint("Not yet implemented."),
 
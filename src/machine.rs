@@ -1,5 +1,13 @@
-use std::collections::VecDeque;
+use std::collections::{
+    HashMap,
+    HashSet,
+    VecDeque,
+};
 
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use wasm_bindgen::JsValue;
 
 use crate::{
@@ -16,7 +24,10 @@ const GROW_SECURITY_INCREASE_THOUSANDTHS: usize = 4;
 
 pub const EXEC_MEMORY_USAGE_HUNDREDTHS: u64 = 175;
 
-#[derive(Clone, Debug)]
+/// File on home the network map is cached in.
+const NETWORK_MAP_FILE: &str = "network_map.txt";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Machine {
     hostname: String,
     degree: usize,
@@ -36,7 +47,8 @@ pub struct Machine {
 impl Machine {
     /// Obtains further details about a machine.
     ///
-    /// These details are constant until the next augmentation.
+    /// These details are constant until the next augmentation, except for
+    /// the cores of the machines we own, which can be upgraded.
     fn get_further_details(
         &mut self,
         ns: &NsWrapper,
@@ -407,3 +419,241 @@ pub fn get_machines(ns: &NsWrapper) -> Vec<Machine> {
 
     traversed
 }
+
+/// Returns every link between two machines, including those that
+/// `get_machines` doesn't use to reach a machine. Each link is listed once.
+pub fn get_network_edges<'a>(
+    ns: &NsWrapper,
+    machines: impl Iterator<Item = &'a Machine>,
+) -> Vec<(String, String)> {
+    let mut edges = vec![];
+
+    for machine in machines {
+        let hostname = machine.get_hostname().to_owned();
+
+        for neighbour in ns.scan(Some(&hostname)) {
+            // order both ends so that each link shows up the same way from
+            // either side
+            match hostname < neighbour {
+                true => edges.push((hostname.clone(), neighbour)),
+                false => edges.push((neighbour, hostname.clone())),
+            }
+        }
+    }
+
+    edges.sort();
+    edges.dedup();
+    edges
+}
+
+/// Time the last augmentations were installed, which also changes when a new
+/// BitNode is entered.
+fn get_last_aug_reset(ns: &NsWrapper) -> f64 {
+    get_attribute(&ns.get_reset_info(), "lastAugReset", JsValue::as_f64)
+        .unwrap()
+        .unwrap()
+}
+
+/// Every machine on the network, cached on home so that their details don't
+/// have to be fetched again on every run.
+///
+/// The details of a machine are constant until the next augmentation, so
+/// the map is rebuilt after one. Otherwise, only the links between machines
+/// are scanned again to find the new ones.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkMap {
+    machines: Vec<Machine>,
+    /// `lastAugReset` of the reset info when the map was built. Maps cached
+    /// before it was recorded are always rebuilt.
+    #[serde(default)]
+    last_aug_reset: f64,
+    #[serde(skip)]
+    index: HashMap<String, usize>,
+}
+
+impl NetworkMap {
+    fn from_machines(machines: Vec<Machine>) -> NetworkMap {
+        let mut retval = NetworkMap {
+            machines,
+            last_aug_reset: 0.,
+            index: HashMap::new(),
+        };
+
+        retval.reindex();
+        retval
+    }
+
+    fn reindex(&mut self) {
+        self.index = self
+            .machines
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (m.get_hostname().to_owned(), i))
+            .collect();
+    }
+
+    /// Loads the cached map, picks up the machines that were added or removed
+    /// since, and saves it back if anything changed. The map is rebuilt if
+    /// augmentations were installed since it was cached.
+    pub fn load(ns: &NsWrapper) -> NetworkMap {
+        let cached = serde_json::from_str::<NetworkMap>(
+            &ns.read(NETWORK_MAP_FILE),
+        );
+
+        let Ok(mut map) = cached
+        else {
+            return NetworkMap::rebuild(ns);
+        };
+
+        if map.last_aug_reset != get_last_aug_reset(ns) {
+            return NetworkMap::rebuild(ns);
+        }
+
+        map.reindex();
+        if map.refresh(ns) {
+            map.save(ns);
+        }
+
+        map
+    }
+
+    /// Forgets the cached map, and fetches everything again.
+    pub fn rebuild(ns: &NsWrapper) -> NetworkMap {
+        let mut map = NetworkMap::from_machines(get_machines(ns));
+        map.last_aug_reset = get_last_aug_reset(ns);
+        map.save(ns);
+        map
+    }
+
+    fn save(
+        &self,
+        ns: &NsWrapper,
+    ) {
+        ns.write(NETWORK_MAP_FILE, &serde_json::to_string(self).unwrap(), 'w');
+    }
+
+    /// Walks the links of the network, only fetching the details of machines
+    /// we haven't seen yet, and forgets the machines that are gone. The
+    /// details of the machines we own are fetched again, since they can be
+    /// upgraded. Returns true if the map changed.
+    pub fn refresh(
+        &mut self,
+        ns: &NsWrapper,
+    ) -> bool {
+        let mut changed = false;
+
+        if !self.contains("home") {
+            self.machines.insert(0, Machine::home(ns));
+            self.reindex();
+            changed = true;
+        }
+
+        let mut visited = HashSet::from(["home".to_owned()]);
+        let mut pending = VecDeque::from(["home".to_owned()]);
+
+        while let Some(hostname) = pending.pop_front() {
+            for child_name in ns.scan(Some(&hostname)) {
+                if !visited.insert(child_name.clone()) {
+                    continue;
+                }
+
+                if !self.contains(&child_name) {
+                    let parent = self.get(&hostname).unwrap();
+                    let child = parent.create_child(ns, child_name.clone());
+
+                    self.index.insert(child_name.clone(), self.machines.len());
+                    self.machines.push(child);
+                    changed = true;
+                }
+
+                pending.push_back(child_name);
+            }
+        }
+
+        // deleted servers, for instance
+        let count = self.machines.len();
+        self.machines.retain(|m| visited.contains(m.get_hostname()));
+
+        if self.machines.len() != count {
+            self.reindex();
+            changed = true;
+        }
+
+        let owned = self.machines.iter_mut().filter(|m| m.is_player_owned());
+        for machine in owned {
+            let cpu_cores = machine.cpu_cores;
+            machine.get_further_details(ns);
+            changed |= machine.cpu_cores != cpu_cores;
+        }
+
+        changed
+    }
+
+    pub fn get(
+        &self,
+        hostname: &str,
+    ) -> Option<&Machine> {
+        self.index.get(hostname).map(|i| &self.machines[*i])
+    }
+
+    pub fn contains(
+        &self,
+        hostname: &str,
+    ) -> bool {
+        self.index.contains_key(hostname)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Machine> {
+        self.machines.iter()
+    }
+
+    pub fn into_machines(self) -> Vec<Machine> {
+        self.machines
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Machine,
+        NetworkMap,
+    };
+
+    fn make_machine(hostname: &str) -> Machine {
+        Machine {
+            hostname: hostname.to_owned(),
+            traversal: vec!["home".to_owned(), hostname.to_owned()],
+            degree: 1,
+            max_money: 1000,
+            ..Machine::dummy()
+        }
+    }
+
+    #[test]
+    fn network_map_round_trip() {
+        let mut map = NetworkMap::from_machines(vec![
+            make_machine("n00dles"),
+            make_machine("CSEC"),
+        ]);
+        map.last_aug_reset = 1234.;
+        assert_eq!(map.get("CSEC").unwrap().get_hostname(), "CSEC");
+
+        let json = serde_json::to_string(&map).unwrap();
+        assert!(!json.contains("index"));
+
+        let mut loaded = serde_json::from_str::<NetworkMap>(&json).unwrap();
+        assert!(loaded.get("CSEC").is_none());
+        assert_eq!(loaded.last_aug_reset, 1234.);
+
+        loaded.reindex();
+        let machine = loaded.get("n00dles").unwrap();
+        assert_eq!(machine.get_max_money(), 1000);
+        assert_eq!(machine.get_traversal(), ["home", "n00dles"]);
+        assert!(!loaded.contains("foodnstuff"));
+
+        let old = json.replace(",\"last_aug_reset\":1234.0", "");
+        assert_ne!(old, json);
+        let loaded = serde_json::from_str::<NetworkMap>(&old).unwrap();
+        assert_eq!(loaded.last_aug_reset, 0.);
+    }
+}
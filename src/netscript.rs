@@ -1,6 +1,9 @@
 use std::sync::Mutex;
 
-use js_sys::JsString;
+use js_sys::{
+    Array,
+    JsString,
+};
 use wasm_bindgen::{
     prelude::*,
     JsValue,
@@ -71,6 +74,24 @@ extern "C" {
         pid: i32,
     ) -> bool;
 
+    #[wasm_bindgen(method, getter)]
+    fn pid(this: &NS) -> i32;
+
+    #[wasm_bindgen(method)]
+    fn ps(
+        this: &NS,
+        host: &str,
+    ) -> Vec<JsValue>;
+
+    #[wasm_bindgen(method)]
+    fn getResetInfo(this: &NS) -> JsValue;
+
+    #[wasm_bindgen(method)]
+    fn getRunningScript(
+        this: &NS,
+        pid: i32,
+    ) -> JsValue;
+
     #[wasm_bindgen(method)]
     fn scan(
         this: &NS,
@@ -184,6 +205,12 @@ extern "C" {
         mode: char,
     );
 
+    #[wasm_bindgen(method)]
+    fn read(
+        this: &NS,
+        filename: &str,
+    ) -> String;
+
     #[wasm_bindgen(method)]
     fn scp(
         this: &NS,
@@ -192,6 +219,14 @@ extern "C" {
         source: &str,
     ) -> bool;
 
+    #[wasm_bindgen(method, js_name = scp)]
+    fn scpMany(
+        this: &NS,
+        files: Array,
+        destination: &str,
+        source: &str,
+    ) -> bool;
+
     #[wasm_bindgen(method)]
     fn fileExists(
         this: &NS,
@@ -216,12 +251,305 @@ extern "C" {
     #[wasm_bindgen(method)]
     fn getHostname(this: &NS) -> JsValue;
 
+    #[wasm_bindgen(method)]
+    fn getPurchasedServers(this: &NS) -> Vec<JsValue>;
+
+    #[wasm_bindgen(method)]
+    fn getPurchasedServerLimit(this: &NS) -> f64;
+
+    #[wasm_bindgen(method)]
+    fn getPurchasedServerMaxRam(this: &NS) -> f64;
+
+    #[wasm_bindgen(method)]
+    fn getPurchasedServerCost(
+        this: &NS,
+        ram: f64,
+    ) -> f64;
+
+    #[wasm_bindgen(method)]
+    fn getPurchasedServerUpgradeCost(
+        this: &NS,
+        hostname: &str,
+        ram: f64,
+    ) -> f64;
+
+    #[wasm_bindgen(method)]
+    fn purchaseServer(
+        this: &NS,
+        hostname: &str,
+        ram: f64,
+    ) -> String;
+
+    #[wasm_bindgen(method)]
+    fn upgradePurchasedServer(
+        this: &NS,
+        hostname: &str,
+        ram: f64,
+    ) -> bool;
+
+    #[wasm_bindgen(method)]
+    fn deleteServer(
+        this: &NS,
+        hostname: &str,
+    ) -> bool;
+
+    #[wasm_bindgen(method, getter)]
+    fn singularity(this: &NS) -> Singularity;
+
+    pub type Singularity;
+
+    #[wasm_bindgen(method)]
+    fn connect(
+        this: &Singularity,
+        hostname: &str,
+    ) -> bool;
+
+    #[wasm_bindgen(catch, method)]
+    async fn installBackdoor(this: &Singularity) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(method)]
+    fn getCurrentServer(this: &Singularity) -> String;
+
+    #[wasm_bindgen(method, getter)]
+    fn hacknet(this: &NS) -> Hacknet;
+
+    pub type Hacknet;
+
+    #[wasm_bindgen(method)]
+    fn numNodes(this: &Hacknet) -> f64;
+
+    #[wasm_bindgen(method)]
+    fn maxNumNodes(this: &Hacknet) -> f64;
+
+    #[wasm_bindgen(method)]
+    fn getPurchaseNodeCost(this: &Hacknet) -> f64;
+
+    #[wasm_bindgen(method)]
+    fn purchaseNode(this: &Hacknet) -> f64;
+
+    #[wasm_bindgen(method)]
+    fn getNodeStats(
+        this: &Hacknet,
+        index: f64,
+    ) -> JsValue;
+
+    #[wasm_bindgen(method)]
+    fn getLevelUpgradeCost(
+        this: &Hacknet,
+        index: f64,
+        n: f64,
+    ) -> f64;
+
+    #[wasm_bindgen(method)]
+    fn upgradeLevel(
+        this: &Hacknet,
+        index: f64,
+        n: f64,
+    ) -> bool;
+
+    #[wasm_bindgen(method)]
+    fn getRamUpgradeCost(
+        this: &Hacknet,
+        index: f64,
+        n: f64,
+    ) -> f64;
+
+    #[wasm_bindgen(method)]
+    fn upgradeRam(
+        this: &Hacknet,
+        index: f64,
+        n: f64,
+    ) -> bool;
+
+    #[wasm_bindgen(method)]
+    fn getCoreUpgradeCost(
+        this: &Hacknet,
+        index: f64,
+        n: f64,
+    ) -> f64;
+
+    #[wasm_bindgen(method)]
+    fn upgradeCore(
+        this: &Hacknet,
+        index: f64,
+        n: f64,
+    ) -> bool;
+
+    #[wasm_bindgen(method)]
+    fn numHashes(this: &Hacknet) -> f64;
+
+    #[wasm_bindgen(catch, method)]
+    fn hashCost(
+        this: &Hacknet,
+        upgrade: &str,
+    ) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(catch, method)]
+    fn spendHashes(
+        this: &Hacknet,
+        upgrade: &str,
+        target: Option<&str>,
+    ) -> Result<bool, JsValue>;
+
+    #[wasm_bindgen(method, getter)]
+    fn stock(this: &NS) -> TIX;
+
+    pub type TIX;
+
+    #[wasm_bindgen(catch, method)]
+    fn getPosition(
+        this: &TIX,
+        sym: &str,
+    ) -> Result<Vec<JsValue>, JsValue>;
+
+    #[wasm_bindgen(method)]
+    fn hasTIXAPIAccess(this: &TIX) -> bool;
+
+    #[wasm_bindgen(method)]
+    fn has4SDataTIXAPI(this: &TIX) -> bool;
+
+    #[wasm_bindgen(catch, method)]
+    fn getSymbols(this: &TIX) -> Result<Vec<JsValue>, JsValue>;
+
+    #[wasm_bindgen(catch, method)]
+    fn getPrice(
+        this: &TIX,
+        sym: &str,
+    ) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(catch, method)]
+    fn getAskPrice(
+        this: &TIX,
+        sym: &str,
+    ) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(catch, method)]
+    fn getBidPrice(
+        this: &TIX,
+        sym: &str,
+    ) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(catch, method)]
+    fn getForecast(
+        this: &TIX,
+        sym: &str,
+    ) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(catch, method)]
+    fn getVolatility(
+        this: &TIX,
+        sym: &str,
+    ) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(catch, method)]
+    fn getMaxShares(
+        this: &TIX,
+        sym: &str,
+    ) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(catch, method)]
+    fn buyStock(
+        this: &TIX,
+        sym: &str,
+        shares: f64,
+    ) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(catch, method)]
+    fn sellStock(
+        this: &TIX,
+        sym: &str,
+        shares: f64,
+    ) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(catch, method)]
+    fn buyShort(
+        this: &TIX,
+        sym: &str,
+        shares: f64,
+    ) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(catch, method)]
+    fn sellShort(
+        this: &TIX,
+        sym: &str,
+        shares: f64,
+    ) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(method)]
+    fn rm(
+        this: &NS,
+        name: &str,
+        host: Option<&str>,
+    ) -> bool;
+
+    #[wasm_bindgen(method)]
+    fn scriptKill(
+        this: &NS,
+        script: &str,
+        host: &str,
+    ) -> bool;
+
+    #[wasm_bindgen(method)]
+    fn peek(
+        this: &NS,
+        port: i32,
+    ) -> JsValue;
+
+    #[wasm_bindgen(method)]
+    fn readPort(
+        this: &NS,
+        port: i32,
+    ) -> JsValue;
+
+    #[wasm_bindgen(method)]
+    fn writePort(
+        this: &NS,
+        port: i32,
+        data: &str,
+    ) -> JsValue;
+
+    #[wasm_bindgen(method)]
+    fn tryWritePort(
+        this: &NS,
+        port: i32,
+        data: &str,
+    ) -> bool;
+
+    #[wasm_bindgen(method)]
+    fn clearPort(
+        this: &NS,
+        port: i32,
+    );
+
+    #[wasm_bindgen(method)]
+    fn getPortHandle(
+        this: &NS,
+        port: i32,
+    ) -> NetscriptPort;
+
+    pub type NetscriptPort;
+
+    #[wasm_bindgen(method)]
+    async fn nextWrite(this: &NetscriptPort);
+
     pub type Server;
 
     pub type Date;
 
     #[wasm_bindgen(static_method_of = Date)]
     pub fn now() -> f64;
+
+    /// High resolution timestamp in milliseconds. Only useful for measuring
+    /// durations.
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    pub fn performance_now() -> f64;
+}
+
+/// What Netscript returns when reading from an empty port.
+pub const NULL_PORT_DATA: &str = "NULL PORT DATA";
+
+fn is_null_port_data(data: &JsValue) -> bool {
+    data.as_string().as_deref() == Some(NULL_PORT_DATA)
 }
 
 pub struct NsWrapper<'a>(Mutex<&'a NS>);
@@ -282,6 +610,214 @@ impl<'a> NsWrapper<'a> {
             .collect::<Vec<_>>()
     }
 
+    pub fn get_purchased_servers(&self) -> Vec<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .getPurchasedServers()
+            .into_iter()
+            .map(|m| m.as_string().unwrap())
+            .collect::<Vec<_>>()
+    }
+
+    pub fn get_purchased_server_limit(&self) -> usize {
+        self.0.lock().unwrap().getPurchasedServerLimit() as usize
+    }
+
+    pub fn get_purchased_server_max_ram(&self) -> f64 {
+        self.0.lock().unwrap().getPurchasedServerMaxRam()
+    }
+
+    /// Returns the cost of a server with `ram` GB, or infinity if `ram` is not
+    /// a valid amount.
+    pub fn get_purchased_server_cost(
+        &self,
+        ram: f64,
+    ) -> f64 {
+        self.0.lock().unwrap().getPurchasedServerCost(ram)
+    }
+
+    /// Returns the cost of upgrading a server to `ram` GB, or infinity if it
+    /// cannot be upgraded to that amount.
+    pub fn get_purchased_server_upgrade_cost(
+        &self,
+        hostname: &str,
+        ram: f64,
+    ) -> f64 {
+        self.0
+            .lock()
+            .unwrap()
+            .getPurchasedServerUpgradeCost(hostname, ram)
+    }
+
+    /// Returns the hostname of the new server, or `None` if the purchase
+    /// failed.
+    pub fn purchase_server(
+        &self,
+        hostname: &str,
+        ram: f64,
+    ) -> Option<String> {
+        let hostname = self.0.lock().unwrap().purchaseServer(hostname, ram);
+        (!hostname.is_empty()).then_some(hostname)
+    }
+
+    pub fn upgrade_purchased_server(
+        &self,
+        hostname: &str,
+        ram: f64,
+    ) -> bool {
+        self.0.lock().unwrap().upgradePurchasedServer(hostname, ram)
+    }
+
+    pub fn delete_server(
+        &self,
+        hostname: &str,
+    ) -> bool {
+        self.0.lock().unwrap().deleteServer(hostname)
+    }
+
+    /// Connects the terminal to a neighbour of the current server, or to a
+    /// backdoored server. Home can always be connected to.
+    pub fn singularity_connect(
+        &self,
+        hostname: &str,
+    ) -> bool {
+        self.0.lock().unwrap().singularity().connect(hostname)
+    }
+
+    /// Installs a backdoor on the server the terminal is connected to.
+    pub async fn singularity_install_backdoor(&self) -> Result<(), JsValue> {
+        // The guard must not be held across the await, or any other use of
+        // the wrapper while the backdoor is installed would deadlock.
+        let singularity = self.0.lock().unwrap().singularity();
+        singularity.installBackdoor().await.map(|_| ())
+    }
+
+    pub fn singularity_get_current_server(&self) -> String {
+        self.0.lock().unwrap().singularity().getCurrentServer()
+    }
+
+    pub fn hacknet_num_nodes(&self) -> usize {
+        self.0.lock().unwrap().hacknet().numNodes() as usize
+    }
+
+    pub fn hacknet_max_num_nodes(&self) -> usize {
+        self.0.lock().unwrap().hacknet().maxNumNodes() as usize
+    }
+
+    pub fn hacknet_get_purchase_node_cost(&self) -> f64 {
+        self.0.lock().unwrap().hacknet().getPurchaseNodeCost()
+    }
+
+    /// Returns the index of the new node, or `None` if the purchase failed.
+    pub fn hacknet_purchase_node(&self) -> Option<usize> {
+        let index = self.0.lock().unwrap().hacknet().purchaseNode();
+        (0. <= index).then_some(index as usize)
+    }
+
+    pub fn hacknet_get_node_stats(
+        &self,
+        index: usize,
+    ) -> JsValue {
+        self.0.lock().unwrap().hacknet().getNodeStats(index as f64)
+    }
+
+    /// Returns infinity if the node cannot be upgraded any further.
+    pub fn hacknet_get_level_upgrade_cost(
+        &self,
+        index: usize,
+        n: usize,
+    ) -> f64 {
+        self.0
+            .lock()
+            .unwrap()
+            .hacknet()
+            .getLevelUpgradeCost(index as f64, n as f64)
+    }
+
+    pub fn hacknet_upgrade_level(
+        &self,
+        index: usize,
+        n: usize,
+    ) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .hacknet()
+            .upgradeLevel(index as f64, n as f64)
+    }
+
+    /// Returns infinity if the node cannot be upgraded any further.
+    pub fn hacknet_get_ram_upgrade_cost(
+        &self,
+        index: usize,
+        n: usize,
+    ) -> f64 {
+        self.0
+            .lock()
+            .unwrap()
+            .hacknet()
+            .getRamUpgradeCost(index as f64, n as f64)
+    }
+
+    pub fn hacknet_upgrade_ram(
+        &self,
+        index: usize,
+        n: usize,
+    ) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .hacknet()
+            .upgradeRam(index as f64, n as f64)
+    }
+
+    /// Returns infinity if the node cannot be upgraded any further.
+    pub fn hacknet_get_core_upgrade_cost(
+        &self,
+        index: usize,
+        n: usize,
+    ) -> f64 {
+        self.0
+            .lock()
+            .unwrap()
+            .hacknet()
+            .getCoreUpgradeCost(index as f64, n as f64)
+    }
+
+    pub fn hacknet_upgrade_core(
+        &self,
+        index: usize,
+        n: usize,
+    ) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .hacknet()
+            .upgradeCore(index as f64, n as f64)
+    }
+
+    pub fn hacknet_num_hashes(&self) -> f64 {
+        self.0.lock().unwrap().hacknet().numHashes()
+    }
+
+    /// Fails if the upgrade does not exist.
+    pub fn hacknet_hash_cost(
+        &self,
+        upgrade: &str,
+    ) -> Result<f64, JsValue> {
+        self.0.lock().unwrap().hacknet().hashCost(upgrade)
+    }
+
+    /// Fails if the upgrade does not exist, or needs a valid target.
+    pub fn hacknet_spend_hashes(
+        &self,
+        upgrade: &str,
+        target: Option<&str>,
+    ) -> Result<bool, JsValue> {
+        self.0.lock().unwrap().hacknet().spendHashes(upgrade, target)
+    }
+
     pub fn get_server(
         &self,
         host: Option<&str>,
@@ -448,6 +984,15 @@ impl<'a> NsWrapper<'a> {
         self.0.lock().unwrap().write(filename, data, mode)
     }
 
+    /// Returns the contents of a file on the current host, or an empty string
+    /// if it does not exist.
+    pub fn read(
+        &self,
+        filename: &str,
+    ) -> String {
+        self.0.lock().unwrap().read(filename)
+    }
+
     pub fn scp(
         &self,
         file: &str,
@@ -457,6 +1002,18 @@ impl<'a> NsWrapper<'a> {
         self.0.lock().unwrap().scp(file, destination, source)
     }
 
+    /// Copies every file in a single call. Returns true only if all of them
+    /// were copied.
+    pub fn scp_many(
+        &self,
+        files: &[&str],
+        destination: &str,
+        source: &str,
+    ) -> bool {
+        let files = files.iter().map(|f| JsValue::from_str(f)).collect();
+        self.0.lock().unwrap().scpMany(files, destination, source)
+    }
+
     pub fn file_exists(
         &self,
         file: &str,
@@ -472,6 +1029,35 @@ impl<'a> NsWrapper<'a> {
         self.0.lock().unwrap().kill(pid)
     }
 
+    /// Returns the pid of the script calling this.
+    pub fn get_pid(&self) -> i32 {
+        self.0.lock().unwrap().pid()
+    }
+
+    /// Returns the `ProcessInfo` of every script running on the host.
+    pub fn ps(
+        &self,
+        host: &str,
+    ) -> Vec<JsValue> {
+        self.0.lock().unwrap().ps(host)
+    }
+
+    /// Returns when the last augmentations were installed and the current
+    /// BitNode was entered.
+    pub fn get_reset_info(&self) -> JsValue {
+        self.0.lock().unwrap().getResetInfo()
+    }
+
+    /// Returns the `RunningScript` with this pid, or `None` if it is not
+    /// running anymore.
+    pub fn get_running_script(
+        &self,
+        pid: i32,
+    ) -> Option<JsValue> {
+        let script = self.0.lock().unwrap().getRunningScript(pid);
+        (!script.is_null() && !script.is_undefined()).then_some(script)
+    }
+
     pub fn growth_analyze(
         &self,
         host: &str,
@@ -501,4 +1087,192 @@ impl<'a> NsWrapper<'a> {
     ) {
         self.0.lock().unwrap().disableLog(function);
     }
+
+    pub fn rm(
+        &self,
+        name: &str,
+        host: Option<&str>,
+    ) -> bool {
+        self.0.lock().unwrap().rm(name, host)
+    }
+
+    pub fn script_kill(
+        &self,
+        script: &str,
+        host: &str,
+    ) -> bool {
+        self.0.lock().unwrap().scriptKill(script, host)
+    }
+
+    /// Returns the first element of the port without removing it, or `None`
+    /// if the port is empty.
+    pub fn peek(
+        &self,
+        port: usize,
+    ) -> Option<JsValue> {
+        let data = self.0.lock().unwrap().peek(port as i32);
+        (!is_null_port_data(&data)).then_some(data)
+    }
+
+    /// Removes and returns the first element of the port, or `None` if the
+    /// port is empty.
+    pub fn read_port(
+        &self,
+        port: usize,
+    ) -> Option<JsValue> {
+        let data = self.0.lock().unwrap().readPort(port as i32);
+        (!is_null_port_data(&data)).then_some(data)
+    }
+
+    /// Writes to the port. If the port was full, the element that got pushed
+    /// out is returned.
+    pub fn write_port(
+        &self,
+        port: usize,
+        data: &str,
+    ) -> Option<JsValue> {
+        let popped = self.0.lock().unwrap().writePort(port as i32, data);
+        (!popped.is_null() && !popped.is_undefined()).then_some(popped)
+    }
+
+    /// Writes to the port only if it is not full. Returns true if written.
+    pub fn try_write_port(
+        &self,
+        port: usize,
+        data: &str,
+    ) -> bool {
+        self.0.lock().unwrap().tryWritePort(port as i32, data)
+    }
+
+    pub fn clear_port(
+        &self,
+        port: usize,
+    ) {
+        self.0.lock().unwrap().clearPort(port as i32);
+    }
+
+    /// Waits until something gets written to the port.
+    pub async fn next_port_write(
+        &self,
+        port: usize,
+    ) {
+        // don't hold the lock while waiting
+        let handle = self.0.lock().unwrap().getPortHandle(port as i32);
+        handle.nextWrite().await;
+    }
+
+    /// Returns the long shares, their average price, the short shares and
+    /// their average price of a stock.
+    pub fn stock_get_position(
+        &self,
+        symbol: &str,
+    ) -> Result<[f64; 4], JsValue> {
+        let position = self.0.lock().unwrap().stock().getPosition(symbol)?;
+        let mut retval = [0.; 4];
+
+        for (value, js_value) in retval.iter_mut().zip(position.iter()) {
+            *value = js_value.as_f64().unwrap_or(0.);
+        }
+
+        Ok(retval)
+    }
+
+    pub fn stock_has_tix_api_access(&self) -> bool {
+        self.0.lock().unwrap().stock().hasTIXAPIAccess()
+    }
+
+    pub fn stock_has_4s_data_tix_api(&self) -> bool {
+        self.0.lock().unwrap().stock().has4SDataTIXAPI()
+    }
+
+    pub fn stock_get_symbols(&self) -> Result<Vec<String>, JsValue> {
+        let symbols = self.0.lock().unwrap().stock().getSymbols()?;
+
+        Ok(symbols
+            .into_iter()
+            .map(|s| s.as_string().unwrap())
+            .collect::<Vec<_>>())
+    }
+
+    pub fn stock_get_price(
+        &self,
+        symbol: &str,
+    ) -> Result<f64, JsValue> {
+        self.0.lock().unwrap().stock().getPrice(symbol)
+    }
+
+    pub fn stock_get_ask_price(
+        &self,
+        symbol: &str,
+    ) -> Result<f64, JsValue> {
+        self.0.lock().unwrap().stock().getAskPrice(symbol)
+    }
+
+    pub fn stock_get_bid_price(
+        &self,
+        symbol: &str,
+    ) -> Result<f64, JsValue> {
+        self.0.lock().unwrap().stock().getBidPrice(symbol)
+    }
+
+    /// Returns the probability of the price going up on the next tick.
+    /// Requires 4S Market Data.
+    pub fn stock_get_forecast(
+        &self,
+        symbol: &str,
+    ) -> Result<f64, JsValue> {
+        self.0.lock().unwrap().stock().getForecast(symbol)
+    }
+
+    /// Returns the maximum fraction the price can move on the next tick.
+    /// Requires 4S Market Data.
+    pub fn stock_get_volatility(
+        &self,
+        symbol: &str,
+    ) -> Result<f64, JsValue> {
+        self.0.lock().unwrap().stock().getVolatility(symbol)
+    }
+
+    pub fn stock_get_max_shares(
+        &self,
+        symbol: &str,
+    ) -> Result<f64, JsValue> {
+        self.0.lock().unwrap().stock().getMaxShares(symbol)
+    }
+
+    /// Returns the price per share, or zero if the transaction failed.
+    pub fn stock_buy(
+        &self,
+        symbol: &str,
+        shares: f64,
+    ) -> Result<f64, JsValue> {
+        self.0.lock().unwrap().stock().buyStock(symbol, shares)
+    }
+
+    /// Returns the price per share, or zero if the transaction failed.
+    pub fn stock_sell(
+        &self,
+        symbol: &str,
+        shares: f64,
+    ) -> Result<f64, JsValue> {
+        self.0.lock().unwrap().stock().sellStock(symbol, shares)
+    }
+
+    /// Returns the price per share, or zero if the transaction failed.
+    pub fn stock_buy_short(
+        &self,
+        symbol: &str,
+        shares: f64,
+    ) -> Result<f64, JsValue> {
+        self.0.lock().unwrap().stock().buyShort(symbol, shares)
+    }
+
+    /// Returns the price per share, or zero if the transaction failed.
+    pub fn stock_sell_short(
+        &self,
+        symbol: &str,
+        shares: f64,
+    ) -> Result<f64, JsValue> {
+        self.0.lock().unwrap().stock().sellShort(symbol, shares)
+    }
 }
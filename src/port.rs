@@ -0,0 +1,205 @@
+use core::marker::PhantomData;
+
+use js_sys::JSON;
+use serde::{
+    de::DeserializeOwned,
+    Serialize,
+};
+use wasm_bindgen::JsValue;
+
+use crate::netscript::NsWrapper;
+
+/// A Netscript port carrying messages of a single type.
+///
+/// Messages are written as JSON strings, so the other end can be another
+/// instance of this tool as well as a plain JavaScript script using
+/// `JSON.stringify` and `JSON.parse`.
+pub struct PortChannel<T> {
+    port: usize,
+    _message: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for PortChannel<T> {
+    fn clone(&self) -> PortChannel<T> {
+        *self
+    }
+}
+
+impl<T> Copy for PortChannel<T> {}
+
+impl<T> core::fmt::Debug for PortChannel<T> {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        f.debug_tuple("PortChannel").field(&self.port).finish()
+    }
+}
+
+impl<T> PortChannel<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    pub const fn new(port: usize) -> PortChannel<T> {
+        PortChannel {
+            port,
+            _message: PhantomData,
+        }
+    }
+
+    pub fn get_port(&self) -> usize {
+        self.port
+    }
+
+    /// Writes a message. If the port was full, the oldest message gets pushed
+    /// out and is returned.
+    pub fn write(
+        &self,
+        ns: &NsWrapper<'_>,
+        message: &T,
+    ) -> Option<Result<T, serde_json::Error>> {
+        ns.write_port(self.port, &encode(message)).map(|data| decode(&data))
+    }
+
+    /// Writes a message only if the port is not full. Returns true if
+    /// written.
+    pub fn try_write(
+        &self,
+        ns: &NsWrapper<'_>,
+        message: &T,
+    ) -> bool {
+        ns.try_write_port(self.port, &encode(message))
+    }
+
+    /// Removes and decodes the oldest message. Returns `None` if the port is
+    /// empty.
+    pub fn read(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) -> Option<Result<T, serde_json::Error>> {
+        ns.read_port(self.port).map(|data| decode(&data))
+    }
+
+    /// Decodes the oldest message without removing it. Returns `None` if the
+    /// port is empty.
+    pub fn peek(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) -> Option<Result<T, serde_json::Error>> {
+        ns.peek(self.port).map(|data| decode(&data))
+    }
+
+    /// Reads every message waiting on the port.
+    ///
+    /// Anything that cannot be decoded into a message is discarded.
+    pub fn drain(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) -> Vec<T> {
+        core::iter::from_fn(|| self.read(ns))
+            .filter_map(Result::ok)
+            .collect()
+    }
+
+    pub fn clear(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) {
+        ns.clear_port(self.port);
+    }
+
+    /// Waits until something gets written to the port.
+    pub async fn next_write(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) {
+        ns.next_port_write(self.port).await;
+    }
+}
+
+fn encode<T>(message: &T) -> String
+where
+    T: Serialize,
+{
+    // the messages are plain data, so this never fails
+    serde_json::to_string(message).unwrap()
+}
+
+fn decode<T>(data: &JsValue) -> Result<T, serde_json::Error>
+where
+    T: DeserializeOwned,
+{
+    // other scripts may write numbers or objects instead of strings. those
+    // are turned back into their JSON form first.
+    let json = match data.as_string() {
+        Some(json) => json,
+        None => JSON::stringify(data)
+            .ok()
+            .and_then(|json| json.as_string())
+            .unwrap_or_default(),
+    };
+
+    decode_json(&json)
+}
+
+fn decode_json<T>(json: &str) -> Result<T, serde_json::Error>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_str(json)
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{
+        Deserialize,
+        Serialize,
+    };
+
+    use super::{
+        decode_json,
+        encode,
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        pid: usize,
+        action: String,
+        result: f64,
+    }
+
+    #[test]
+    fn messages_are_plain_json() {
+        let message = Message {
+            pid: 42,
+            action: "hack".to_owned(),
+            result: 1.5e6,
+        };
+
+        assert_eq!(
+            encode(&message),
+            r#"{"pid":42,"action":"hack","result":1500000.0}"#
+        );
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let message = Message {
+            pid: 42,
+            action: "say \"hi\"\n".to_owned(),
+            result: -0.25,
+        };
+
+        let json = encode(&message);
+
+        assert_eq!(decode_json::<Message>(&json).unwrap(), message);
+    }
+
+    #[test]
+    fn malformed_messages_are_errors() {
+        assert!(decode_json::<Message>("").is_err());
+        assert!(decode_json::<Message>("NULL PORT DATA").is_err());
+        assert!(decode_json::<Message>(r#"{"pid": 42"#).is_err());
+        assert!(decode_json::<Message>(r#"{"pid": "42"}"#).is_err());
+    }
+}
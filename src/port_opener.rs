@@ -0,0 +1,174 @@
+use crate::{
+    machine::Machine,
+    netscript::NsWrapper,
+};
+
+/// A program that opens a port on a machine, needed before nuking machines
+/// that require open ports.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PortOpener {
+    BruteSsh,
+    FtpCrack,
+    RelaySmtp,
+    HttpWorm,
+    SqlInject,
+}
+
+impl PortOpener {
+    /// Every port opener, from the cheapest to the most expensive.
+    pub const ALL: [PortOpener; 5] = [
+        PortOpener::BruteSsh,
+        PortOpener::FtpCrack,
+        PortOpener::RelaySmtp,
+        PortOpener::HttpWorm,
+        PortOpener::SqlInject,
+    ];
+
+    pub fn filename(&self) -> &'static str {
+        use PortOpener::*;
+
+        match self {
+            BruteSsh => "BruteSSH.exe",
+            FtpCrack => "FTPCrack.exe",
+            RelaySmtp => "relaySMTP.exe",
+            HttpWorm => "HTTPWorm.exe",
+            SqlInject => "SQLInject.exe",
+        }
+    }
+
+    /// Price of the program on the dark web.
+    pub fn cost(&self) -> f64 {
+        use PortOpener::*;
+
+        match self {
+            BruteSsh => 500e3,
+            FtpCrack => 1.5e6,
+            RelaySmtp => 5e6,
+            HttpWorm => 30e6,
+            SqlInject => 250e6,
+        }
+    }
+
+    pub fn open(
+        &self,
+        ns: &NsWrapper,
+        machine: &mut Machine,
+    ) -> bool {
+        use PortOpener::*;
+
+        match self {
+            BruteSsh => machine.run_brute_ssh(ns),
+            FtpCrack => machine.run_ftp_crack(ns),
+            RelaySmtp => machine.run_relay_smtp(ns),
+            HttpWorm => machine.run_http_worm(ns),
+            SqlInject => machine.run_sql_inject(ns),
+        }
+    }
+}
+
+impl std::fmt::Display for PortOpener {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "{}", self.filename())
+    }
+}
+
+/// Returns the port openers found on home, from the cheapest to the most
+/// expensive.
+pub fn get_available_openers(ns: &NsWrapper) -> Vec<PortOpener> {
+    PortOpener::ALL
+        .into_iter()
+        .filter(|opener| ns.file_exists(opener.filename(), "home"))
+        .collect()
+}
+
+/// Returns the cheapest port openers we are missing to open `ports` ports,
+/// or nothing if we have enough of them.
+pub fn get_missing_openers(
+    available: &[PortOpener],
+    ports: usize,
+) -> Vec<PortOpener> {
+    let needed = ports.saturating_sub(available.len());
+
+    PortOpener::ALL
+        .into_iter()
+        .filter(|opener| !available.contains(opener))
+        .take(needed)
+        .collect()
+}
+
+/// What buying one more port opener gets us, on top of the ones bought
+/// before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PurchaseStep {
+    pub opener: PortOpener,
+    /// Machines that can be nuked once the opener is bought.
+    pub machines: usize,
+    /// Sum of the max money of these machines.
+    pub money: u64,
+}
+
+/// Lists the missing port openers from the cheapest, along with the machines
+/// each one unlocks. Any opener opens one more port, so the cheapest are
+/// always bought first.
+///
+/// `locked` holds the required open ports and the max money of the machines
+/// we have the hacking level for but can't nuke yet.
+pub fn plan_purchases(
+    available: &[PortOpener],
+    locked: &[(usize, u64)],
+) -> Vec<PurchaseStep> {
+    PortOpener::ALL
+        .into_iter()
+        .filter(|opener| !available.contains(opener))
+        .enumerate()
+        .map(|(i, opener)| {
+            let ports = available.len() + i + 1;
+            let unlocked = locked.iter().filter(|(p, _)| *p == ports);
+
+            PurchaseStep {
+                opener,
+                machines: unlocked.clone().count(),
+                money: unlocked.map(|(_, money)| money).sum(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        get_missing_openers,
+        plan_purchases,
+        PortOpener,
+    };
+
+    #[test]
+    fn missing_openers_are_the_cheapest() {
+        let available = [PortOpener::BruteSsh, PortOpener::RelaySmtp];
+
+        assert_eq!(get_missing_openers(&available, 2), vec![]);
+        assert_eq!(
+            get_missing_openers(&available, 4),
+            vec![PortOpener::FtpCrack, PortOpener::HttpWorm]
+        );
+    }
+
+    #[test]
+    fn purchases_unlock_machines() {
+        let available = [PortOpener::BruteSsh];
+        let locked = [(2, 100), (2, 50), (3, 1000), (5, 7)];
+
+        let steps = plan_purchases(&available, &locked);
+
+        assert_eq!(steps.len(), 4);
+        assert_eq!(steps[0].opener, PortOpener::FtpCrack);
+        assert_eq!((steps[0].machines, steps[0].money), (2, 150));
+        assert_eq!((steps[1].machines, steps[1].money), (1, 1000));
+        assert_eq!((steps[2].machines, steps[2].money), (0, 0));
+        assert_eq!(steps[3].opener, PortOpener::SqlInject);
+        assert_eq!((steps[3].machines, steps[3].money), (1, 7));
+    }
+}
@@ -0,0 +1,337 @@
+use std::fmt::Write as _;
+
+use clap::Args;
+use js_sys::Array;
+use wasm_bindgen::JsValue;
+
+use crate::{
+    machine::NetworkMap,
+    netscript::NsWrapper,
+    utils::{
+        get_attribute,
+        glob_match,
+    },
+};
+
+#[derive(Args, Debug, Clone)]
+pub struct ProcsMode {
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        help = "only keep the scripts whose filename matches this, e.g. \
+                *.js"
+    )]
+    filename: Option<String>,
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        help = "only keep the scripts whose arguments, joined by spaces, \
+                match this, e.g. *n00dles*"
+    )]
+    args: Option<String>,
+    #[arg(long, help = "sum up the processes of each script")]
+    aggregate: bool,
+    #[arg(long, help = "kill the processes that are kept")]
+    kill: bool,
+}
+
+impl ProcsMode {
+    pub fn execute(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) {
+        if self.kill && self.filename.is_none() && self.args.is_none() {
+            ns.tprint("Refusing to kill every script, give a pattern.");
+            return;
+        }
+
+        let own_pid = ns.get_pid();
+        let processes = get_processes(ns)
+            .into_iter()
+            .filter(|p| p.pid != own_pid)
+            .filter(|p| {
+                p.matches(self.filename.as_deref(), self.args.as_deref())
+            })
+            .collect::<Vec<_>>();
+
+        if processes.is_empty() {
+            ns.tprint("No matching scripts are running.");
+            return;
+        }
+
+        if self.kill {
+            let killed =
+                processes.iter().filter(|p| ns.kill(p.pid)).count();
+
+            ns.tprint(&format!(
+                "Killed {} of {} processes.",
+                killed,
+                processes.len()
+            ));
+            return;
+        }
+
+        let print_str = match self.aggregate {
+            true => format_summaries(&aggregate(&processes)),
+            false => format_processes(&processes),
+        };
+
+        ns.tprint(&print_str);
+    }
+}
+
+/// A script running somewhere on the network.
+#[derive(Debug, Clone, PartialEq)]
+struct Process {
+    hostname: String,
+    pid: i32,
+    filename: String,
+    threads: usize,
+    args: Vec<String>,
+    /// RAM used by all the threads, in GB.
+    ram: f64,
+    /// Seconds since the script was started.
+    uptime: f64,
+    /// Money made per second since the script was started.
+    income: f64,
+}
+
+impl Process {
+    /// Builds the process from the `RunningScript` of a pid, or returns
+    /// `None` if it ended in the meantime.
+    fn get(
+        ns: &NsWrapper<'_>,
+        hostname: &str,
+        pid: i32,
+    ) -> Option<Process> {
+        let script = ns.get_running_script(pid)?;
+        let get = |field| {
+            get_attribute(&script, field, JsValue::as_f64)
+                .unwrap()
+                .unwrap_or(0.)
+        };
+
+        let threads = get("threads") as usize;
+        let uptime = get("onlineRunningTime");
+        let income = match 0. < uptime {
+            true => get("onlineMoneyMade") / uptime,
+            false => 0.,
+        };
+
+        let args = get_attribute(&script, "args", |a| {
+            Some(Array::from(a).iter().map(|arg| format_arg(&arg)).collect())
+        })
+        .unwrap()
+        .unwrap_or_default();
+
+        Some(Process {
+            hostname: hostname.to_owned(),
+            pid,
+            filename: get_attribute(&script, "filename", JsValue::as_string)
+                .unwrap()
+                .unwrap_or_default(),
+            threads,
+            args,
+            ram: get("ramUsage") * threads as f64,
+            uptime,
+            income,
+        })
+    }
+
+    fn matches(
+        &self,
+        filename: Option<&str>,
+        args: Option<&str>,
+    ) -> bool {
+        filename.is_none_or(|p| glob_match(p, &self.filename)) &&
+            args.is_none_or(|p| glob_match(p, &self.args.join(" ")))
+    }
+}
+
+/// Script arguments can be strings, numbers or booleans.
+fn format_arg(arg: &JsValue) -> String {
+    if let Some(arg) = arg.as_string() {
+        arg
+    }
+    else if let Some(arg) = arg.as_f64() {
+        arg.to_string()
+    }
+    else if let Some(arg) = arg.as_bool() {
+        arg.to_string()
+    }
+    else {
+        format!("{:?}", arg)
+    }
+}
+
+/// Lists the scripts running on every machine we have root access to.
+fn get_processes(ns: &NsWrapper<'_>) -> Vec<Process> {
+    let mut processes = vec![];
+
+    for machine in NetworkMap::load(ns).iter() {
+        if !machine.is_root(ns) {
+            continue;
+        }
+
+        let hostname = machine.get_hostname();
+        for info in ns.ps(hostname) {
+            let pid = get_attribute(&info, "pid", JsValue::as_f64)
+                .unwrap()
+                .unwrap() as i32;
+
+            processes.extend(Process::get(ns, hostname, pid));
+        }
+    }
+
+    processes
+}
+
+fn format_processes(processes: &[Process]) -> String {
+    let name_len = processes
+        .iter()
+        .map(|p| p.hostname.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut print_str = "\n".to_owned();
+    for process in processes.iter() {
+        writeln!(
+            &mut print_str,
+            "{: <lnl$}   {: >6}   {: >6}t   {: >9.2} GB   {: >8.0}s   \
+             {: >9.3e}/s   {} {}",
+            process.hostname,
+            process.pid,
+            process.threads,
+            process.ram,
+            process.uptime,
+            process.income,
+            process.filename,
+            process.args.join(" "),
+            lnl = name_len,
+        )
+        .unwrap();
+    }
+
+    print_str
+}
+
+/// The processes of a script, summed up across the network.
+#[derive(Debug, Clone, PartialEq)]
+struct ScriptSummary {
+    filename: String,
+    processes: usize,
+    threads: usize,
+    ram: f64,
+    income: f64,
+}
+
+/// Sums up the processes of each script, the ones using the most RAM first.
+fn aggregate(processes: &[Process]) -> Vec<ScriptSummary> {
+    let mut summaries: Vec<ScriptSummary> = vec![];
+
+    for process in processes.iter() {
+        let position =
+            summaries.iter().position(|s| s.filename == process.filename);
+
+        let summary = match position {
+            Some(position) => &mut summaries[position],
+            None => {
+                summaries.push(ScriptSummary {
+                    filename: process.filename.clone(),
+                    processes: 0,
+                    threads: 0,
+                    ram: 0.,
+                    income: 0.,
+                });
+                summaries.last_mut().unwrap()
+            },
+        };
+
+        summary.processes += 1;
+        summary.threads += process.threads;
+        summary.ram += process.ram;
+        summary.income += process.income;
+    }
+
+    summaries.sort_by(|a, b| b.ram.total_cmp(&a.ram));
+    summaries
+}
+
+fn format_summaries(summaries: &[ScriptSummary]) -> String {
+    let name_len = summaries
+        .iter()
+        .map(|s| s.filename.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut print_str = "\n".to_owned();
+    for summary in summaries.iter() {
+        writeln!(
+            &mut print_str,
+            "{: <lnl$}   {: >5} procs   {: >8}t   {: >10.2} GB   {: >9.3e}/s",
+            summary.filename,
+            summary.processes,
+            summary.threads,
+            summary.ram,
+            summary.income,
+            lnl = name_len,
+        )
+        .unwrap();
+    }
+
+    print_str
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        aggregate,
+        Process,
+    };
+
+    fn make_process(
+        filename: &str,
+        args: &[&str],
+        threads: usize,
+    ) -> Process {
+        Process {
+            hostname: "home".to_owned(),
+            pid: 1,
+            filename: filename.to_owned(),
+            threads,
+            args: args.iter().map(|a| a.to_string()).collect(),
+            ram: threads as f64 * 1.75,
+            uptime: 10.,
+            income: 100.,
+        }
+    }
+
+    #[test]
+    fn processes_match_patterns() {
+        let process = make_process("hack.js", &["n00dles", "1"], 4);
+
+        assert!(process.matches(None, None));
+        assert!(process.matches(Some("*.js"), Some("n00dles*")));
+        assert!(!process.matches(Some("grow.js"), None));
+        assert!(!process.matches(None, Some("foodnstuff*")));
+    }
+
+    #[test]
+    fn aggregates_by_script() {
+        let processes = [
+            make_process("hack.js", &["n00dles"], 1),
+            make_process("grow.js", &["n00dles"], 10),
+            make_process("hack.js", &["foodnstuff"], 3),
+        ];
+
+        let summaries = aggregate(&processes);
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].filename, "grow.js");
+        assert_eq!(summaries[1].filename, "hack.js");
+        assert_eq!(summaries[1].processes, 2);
+        assert_eq!(summaries[1].threads, 4);
+        assert_eq!(summaries[1].ram, 7.);
+        assert_eq!(summaries[1].income, 200.);
+    }
+}
@@ -2,19 +2,47 @@ use core::ops::{
     Deref,
     DerefMut,
 };
-use std::fmt::Write as _;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+};
 
 use clap::{
     Args,
     ValueEnum,
 };
+use serde::Serialize;
 
 use crate::{
+    dot::{
+        render_dot,
+        DotNode,
+        NodeStatus,
+    },
+    filter::MachineSelection,
     machine::{
-        get_machines,
+        get_network_edges,
         Machine,
+        NetworkMap,
     },
     netscript::NsWrapper,
+    port_opener::{
+        get_available_openers,
+        get_missing_openers,
+        plan_purchases,
+        PortOpener,
+    },
+    tree::{
+        render_tree,
+        TreeEntry,
+    },
+    utils::glob_match,
+    time_consts::SECOND,
+    watch::{
+        parse_interval,
+        watch_machines,
+        WatchLimit,
+    },
 };
 
 #[derive(Debug, Clone)]
@@ -34,6 +62,12 @@ impl Deref for ScannedMachine {
     }
 }
 
+impl AsRef<Machine> for ScannedMachine {
+    fn as_ref(&self) -> &Machine {
+        &self.0
+    }
+}
+
 impl DerefMut for ScannedMachine {
     fn deref_mut(&mut self) -> &mut Machine {
         &mut self.0
@@ -45,11 +79,16 @@ pub enum NukeResult {
     JustNuked,
     WasNuked,
     NotNuked,
+    /// The machine needs more open ports than we have port openers for.
+    MissingOpeners(Vec<PortOpener>),
 }
 
+/// Nukes the machine with the port openers found on home, as given by
+/// `get_available_openers`.
 pub fn nuke_machine(
     ns: &NsWrapper,
     machine: &mut Machine,
+    openers: &[PortOpener],
 ) -> NukeResult {
     use NukeResult::*;
 
@@ -61,11 +100,18 @@ pub fn nuke_machine(
         return NotNuked;
     }
 
-    machine.run_brute_ssh(ns);
-    machine.run_ftp_crack(ns);
-    machine.run_relay_smtp(ns);
-    machine.run_http_worm(ns);
-    machine.run_sql_inject(ns);
+    let missing =
+        get_missing_openers(openers, machine.get_required_open_ports());
+    if !missing.is_empty() {
+        return MissingOpeners(missing);
+    }
+
+    for opener in openers
+        .iter()
+        .take(machine.get_required_open_ports())
+    {
+        opener.open(ns, machine);
+    }
 
     if machine.nuke(ns) {
         JustNuked
@@ -79,8 +125,9 @@ impl ScannedMachine {
     fn nuke(
         &mut self,
         ns: &NsWrapper,
+        openers: &[PortOpener],
     ) -> NukeResult {
-        nuke_machine(ns, &mut self.0)
+        nuke_machine(ns, &mut self.0, openers)
     }
 }
 
@@ -89,6 +136,7 @@ pub enum DisplayMode {
     Path,
     Cd,
     Name,
+    Tree,
 }
 
 #[derive(Debug, Copy, Clone, ValueEnum)]
@@ -97,37 +145,387 @@ pub enum ExecMode {
     Nuke,
     Backdoor,
     Sniff,
+    Plan,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Dot,
 }
 
+/// Servers whose backdoor gets us a faction invite, installed before the
+/// others.
+const FACTION_SERVERS: [&str; 5] = [
+    "CSEC",
+    "avmnite-02h",
+    "I.I.I.I",
+    "run4theh111z",
+    "w0r1d_d43m0n",
+];
+
+/// File on home the graph is exported to when no output file is given.
+const DEFAULT_EXPORT_FILE: &str = "network_dot.txt";
+
 #[derive(Args, Debug, Clone)]
 pub struct ScanMode {
     #[arg(long, short, value_enum, default_value_t = ExecMode::Scan)]
     exec: ExecMode,
     #[arg(long, short, value_enum, default_value_t = DisplayMode::Name)]
     display: DisplayMode,
+    #[arg(long, short, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+    #[arg(long, short, help = "write the output to this file on home")]
+    output: Option<String>,
+    #[command(flatten)]
+    selection: MachineSelection,
+    #[arg(long, help = "count the purchased servers instead of drawing them")]
+    collapse_owned: bool,
+    #[arg(
+        long,
+        value_enum,
+        help = "write every link of the network as a graph to the output \
+                file, or network_dot.txt"
+    )]
+    export: Option<ExportFormat>,
+    #[arg(long, help = "forget the cached network map and fetch it again")]
+    rescan: bool,
+    #[arg(
+        long,
+        help = "install the backdoors through Singularity instead of printing \
+                the commands"
+    )]
+    auto: bool,
+    #[command(flatten)]
+    sniff: SniffOptions,
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_interval,
+        conflicts_with_all = ["exec", "export"],
+        help = "keep a table of the machines up to date in the log, \
+                refreshed every this many seconds"
+    )]
+    watch: Option<f64>,
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "watch",
+        help = "stop watching after this many refreshes"
+    )]
+    refreshes: Option<u64>,
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_interval,
+        requires = "watch",
+        conflicts_with = "refreshes",
+        help = "stop watching after this many seconds"
+    )]
+    watch_for: Option<f64>,
+}
+
+// what sniff mode does with the files it finds. this is not a doc comment
+// since clap would show it as the about of the scan subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct SniffOptions {
+    #[arg(
+        long = "glob",
+        value_name = "PATTERN",
+        help = "only keep the files matching this, e.g. *.lit, *.cct or \
+                *.msg. can be repeated"
+    )]
+    globs: Vec<String>,
+    #[arg(
+        long,
+        help = "copy the .lit, .txt and .script files found to the current \
+                host, the text ones in a folder named after their host"
+    )]
+    fetch: bool,
+    #[arg(long, help = "print the contents of the text files found")]
+    cat: bool,
 }
 
 impl ScanMode {
-    pub fn execute(
+    pub async fn execute(
         &self,
-        ns: &NsWrapper,
+        ns: &NsWrapper<'_>,
     ) {
         use ExecMode::*;
 
-        let mut machines = get_machines(ns)
+        let network = match self.rescan {
+            true => NetworkMap::rebuild(ns),
+            false => NetworkMap::load(ns),
+        };
+
+        let mut machines = network
+            .into_machines()
             .into_iter()
             .map(|m| ScannedMachine::from(m))
             .collect::<Vec<_>>();
 
+        // each mode has its own order, which --sort takes precedence over
         match self.exec {
-            Nuke => nuke_mode(ns, &mut *machines, self.display),
-            Scan => scan_mode(ns, &mut *machines, self.display),
-            Sniff => sniff_mode(ns, &mut *machines, self.display),
-            Backdoor => backdoor_mode(ns, &*machines),
+            Scan => machines.sort_by(|m1, m2| {
+                m1.get_min_hacking_skill()
+                    .cmp(&m2.get_min_hacking_skill())
+                    .then(m1.get_max_money().cmp(&m2.get_max_money()))
+            }),
+            Nuke => machines.sort_by(|m1, m2| {
+                m1.get_degree()
+                    .cmp(&m2.get_degree())
+                    .then(m1.get_hostname().cmp(m2.get_hostname()))
+            }),
+            Sniff | Backdoor | Plan => (),
+        }
+
+        // these modes print commands or advice rather than machines
+        if matches!(self.exec, Backdoor | Plan) &&
+            self.format != OutputFormat::Table
+        {
+            ns.tprint(
+                "--format only applies to the scan, nuke and sniff modes.",
+            );
+            return;
+        }
+
+        let mut machines = self.selection.apply(ns, machines);
+
+        if let Some(interval) = self.watch {
+            let limit = match (self.refreshes, self.watch_for) {
+                (Some(count), _) => WatchLimit::Refreshes(count),
+                (None, Some(secs)) => WatchLimit::Duration(secs * SECOND),
+                (None, None) => WatchLimit::Forever,
+            };
+
+            let machines = machines.into_iter().map(|m| m.0).collect();
+            watch_machines(ns, machines, interval, limit).await;
+
+            return;
+        }
+
+        if let Some(ExportFormat::Dot) = self.export {
+            let filename =
+                self.output.as_deref().unwrap_or(DEFAULT_EXPORT_FILE);
+
+            if !write_on_home(ns, filename, &export_dot(ns, &machines)) {
+                ns.tprint(&format!("Unable to copy {} to home.", filename));
+                return;
+            }
+
+            ns.tprint(&format!(
+                "Exported {} machines to {}.",
+                machines.len(),
+                filename
+            ));
+
+            return;
+        }
+
+        let output = match self.exec {
+            Nuke => nuke_mode(ns, &mut machines, self.display, self.format),
+            Scan => scan_mode(
+                ns,
+                &machines,
+                self.display,
+                self.format,
+                self.collapse_owned,
+            ),
+            Sniff => sniff_mode(
+                ns,
+                &machines,
+                self.display,
+                self.format,
+                &self.sniff,
+            ),
+            Backdoor if self.auto => auto_backdoor_mode(ns, &machines).await,
+            Backdoor => backdoor_mode(ns, &machines),
+            Plan => plan_mode(ns, &machines),
+        };
+
+        match &self.output {
+            Some(filename) => match write_on_home(ns, filename, &output) {
+                true => {
+                    ns.tprint(&format!("Wrote the output to {}.", filename))
+                },
+                false => {
+                    ns.tprint(&format!("Unable to copy {} to home.", filename))
+                },
+            },
+            None => ns.tprint(&output),
         }
     }
 }
 
+/// Writes a file on home, even when the script runs on another host. The
+/// file is written on the current host first, since scripts can only write
+/// there, then copied over. Returns false if the copy failed.
+fn write_on_home(
+    ns: &NsWrapper,
+    filename: &str,
+    contents: &str,
+) -> bool {
+    ns.write(filename, contents, 'w');
+
+    let current_hostname = ns.get_hostname();
+    current_hostname == "home" || ns.scp(filename, "home", &current_hostname)
+}
+
+/// Everything we know about a machine, for other scripts and tools to read.
+#[derive(Debug, Clone, Serialize)]
+pub struct MachineRecord {
+    hostname: String,
+    traversal: Vec<String>,
+    degree: usize,
+    ip_address: String,
+    organization: String,
+    max_money: u64,
+    hacking_skill: usize,
+    min_security: f64,
+    cpu_cores: usize,
+    required_open_ports: usize,
+    root: bool,
+    backdoor: bool,
+    /// Port openers we need to buy to nuke the machine, only in nuke mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    missing_openers: Option<Vec<String>>,
+    /// Files found on the machine, only in sniff mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<Vec<String>>,
+    /// Where the files were copied to, only in sniff mode with `--fetch`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fetched: Option<Vec<String>>,
+    /// Contents of the text files by filename, only in sniff mode with
+    /// `--cat`. In CSV, they are held by a single field as a JSON object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contents: Option<BTreeMap<String, String>>,
+}
+
+impl MachineRecord {
+    const CSV_HEADER: &'static str = "hostname,traversal,degree,ip_address,\
+                                      organization,max_money,hacking_skill,\
+                                      min_security,cpu_cores,\
+                                      required_open_ports,root,backdoor";
+
+    pub fn new(
+        ns: &NsWrapper,
+        machine: &Machine,
+    ) -> MachineRecord {
+        MachineRecord {
+            hostname: machine.get_hostname().to_owned(),
+            traversal: machine.get_traversal().to_vec(),
+            degree: machine.get_degree(),
+            ip_address: machine.get_ip_address().to_owned(),
+            organization: machine.get_organization_name().to_owned(),
+            max_money: machine.get_max_money(),
+            hacking_skill: machine.get_min_hacking_skill(),
+            min_security: machine.get_min_security(),
+            cpu_cores: machine.get_cpu_cores(),
+            required_open_ports: machine.get_required_open_ports(),
+            root: machine.is_root(ns),
+            backdoor: machine.is_backdoored(ns),
+            missing_openers: None,
+            files: None,
+            fetched: None,
+            contents: None,
+        }
+    }
+
+    fn write_csv_row(
+        &self,
+        writable: &mut String,
+    ) {
+        let mut fields = vec![
+            escape_csv(&self.hostname),
+            escape_csv(&self.traversal.join("/")),
+            self.degree.to_string(),
+            escape_csv(&self.ip_address),
+            escape_csv(&self.organization),
+            self.max_money.to_string(),
+            self.hacking_skill.to_string(),
+            self.min_security.to_string(),
+            self.cpu_cores.to_string(),
+            self.required_open_ports.to_string(),
+            self.root.to_string(),
+            self.backdoor.to_string(),
+        ];
+
+        if let Some(missing_openers) = &self.missing_openers {
+            fields.push(escape_csv(&missing_openers.join(";")));
+        }
+
+        if let Some(files) = &self.files {
+            fields.push(escape_csv(&files.join(";")));
+        }
+
+        if let Some(fetched) = &self.fetched {
+            fields.push(escape_csv(&fetched.join(";")));
+        }
+
+        if let Some(contents) = &self.contents {
+            fields.push(escape_csv(&serde_json::to_string(contents).unwrap()));
+        }
+
+        writeln!(writable, "{}", fields.join(",")).unwrap();
+    }
+}
+
+/// Quotes a CSV field if it needs to be.
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    }
+    else {
+        field.to_owned()
+    }
+}
+
+/// Serializes the records into one of the machine-readable formats. Tables
+/// are laid out by each mode instead, so they never end up here.
+fn format_records(
+    records: &[MachineRecord],
+    format: OutputFormat,
+) -> String {
+    match format {
+        OutputFormat::Table => unreachable!("tables are not records"),
+        OutputFormat::Json => serde_json::to_string_pretty(records).unwrap(),
+        OutputFormat::Csv => {
+            let has_missing_openers =
+                records.iter().any(|r| r.missing_openers.is_some());
+            let has_files = records.iter().any(|r| r.files.is_some());
+            let has_fetched = records.iter().any(|r| r.fetched.is_some());
+            let has_contents = records.iter().any(|r| r.contents.is_some());
+            let mut retval = MachineRecord::CSV_HEADER.to_owned();
+
+            if has_missing_openers {
+                retval += ",missing_openers";
+            }
+            if has_files {
+                retval += ",files";
+            }
+            if has_fetched {
+                retval += ",fetched";
+            }
+            if has_contents {
+                retval += ",contents";
+            }
+            retval += "\n";
+
+            for record in records.iter() {
+                record.write_csv_row(&mut retval);
+            }
+
+            retval
+        },
+    }
+}
+
 // hostname, ip address, organization, max money hacking skill, min security,
 // cpu cores, required open ports
 pub fn get_longest_stuff<'a>(
@@ -179,16 +577,25 @@ pub fn get_longest_stuff<'a>(
 
 fn scan_mode(
     ns: &NsWrapper,
-    network: &mut [ScannedMachine],
+    network: &[ScannedMachine],
     display_mode: DisplayMode,
-) {
+    format: OutputFormat,
+    collapse_owned: bool,
+) -> String {
     use DisplayMode::*;
 
-    network.sort_unstable_by(|m1, m2| {
-        m1.get_min_hacking_skill()
-            .cmp(&m2.get_min_hacking_skill())
-            .then(m1.get_max_money().cmp(&m2.get_max_money()))
-    });
+    if format != OutputFormat::Table {
+        let records = network
+            .iter()
+            .map(|m| MachineRecord::new(ns, m))
+            .collect::<Vec<_>>();
+
+        return format_records(&records, format);
+    }
+
+    if let Tree = display_mode {
+        return tree_mode(ns, network, collapse_owned);
+    }
 
     let (name_len, ip_len, org_len, mm_len, hs_len, sec_len, cc_len, rop_len) =
         get_longest_stuff(network.iter().map(|m| &m.0));
@@ -212,7 +619,7 @@ fn scan_mode(
                 write!(&mut print_str, "\n ").unwrap();
             },
 
-            Name => {
+            Name | Tree => {
                 write!(
                     &mut print_str,
                     "{: <lnl$}   ",
@@ -260,30 +667,118 @@ fn scan_mode(
         .unwrap();
     }
 
-    ns.tprint(&*print_str);
+    print_str
+}
+
+fn export_dot(
+    ns: &NsWrapper,
+    network: &[ScannedMachine],
+) -> String {
+    let nodes = network
+        .iter()
+        .map(|machine| {
+            let status = if machine.is_player_owned() {
+                NodeStatus::Owned
+            }
+            else if machine.is_backdoored(ns) {
+                NodeStatus::Backdoored
+            }
+            else if machine.is_root(ns) {
+                NodeStatus::Rooted
+            }
+            else {
+                NodeStatus::Locked
+            };
+
+            DotNode {
+                hostname: machine.get_hostname().to_owned(),
+                status,
+                max_money: machine.get_max_money(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let edges = get_network_edges(ns, network.iter().map(|m| &m.0));
+
+    render_dot(&nodes, &edges)
+}
+
+fn tree_mode(
+    ns: &NsWrapper,
+    network: &[ScannedMachine],
+    collapse_owned: bool,
+) -> String {
+    let entries = network
+        .iter()
+        .map(|machine| {
+            let is_root = match machine.is_root(ns) {
+                true => "ROOT",
+                false => "user",
+            };
+
+            let is_backdoored = match machine.is_backdoored(ns) {
+                true => "BD",
+                false => "  ",
+            };
+
+            TreeEntry {
+                traversal: machine.get_traversal(),
+                annotation: format!(
+                    "{} {}   Hack Lvl {}   {} Ports",
+                    is_root,
+                    is_backdoored,
+                    machine.get_min_hacking_skill(),
+                    machine.get_required_open_ports(),
+                ),
+                collapsible: collapse_owned &&
+                    machine.is_player_owned() &&
+                    machine.get_hostname() != "home",
+            }
+        })
+        .collect::<Vec<_>>();
+
+    format!("\n{}", render_tree(&entries))
 }
 
 fn nuke_mode(
     ns: &NsWrapper,
     network: &mut [ScannedMachine],
     display_mode: DisplayMode,
-) {
+    format: OutputFormat,
+) -> String {
     use DisplayMode::*;
     use NukeResult::*;
 
-    let mut nuked_machines = network
+    let openers = get_available_openers(ns);
+
+    let nuked_machines = network
         .iter_mut()
         .map(|m| {
-            let nuke_stat = m.nuke(ns);
+            let nuke_stat = m.nuke(ns, &openers);
             (m, nuke_stat)
         })
         .collect::<Vec<_>>();
 
-    nuked_machines.sort_unstable_by(|(m1, _), (m2, _)| {
-        m1.get_degree()
-            .cmp(&m2.get_degree())
-            .then(m1.get_hostname().cmp(&m2.get_hostname()))
-    });
+    if format != OutputFormat::Table {
+        let records = nuked_machines
+            .iter()
+            .map(|(m, status)| {
+                let missing_openers = match status {
+                    MissingOpeners(missing) => {
+                        missing.iter().map(|o| o.to_string()).collect()
+                    },
+                    _ => vec![],
+                };
+
+                MachineRecord {
+                    missing_openers: Some(missing_openers),
+                    ..MachineRecord::new(ns, m)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        return format_records(&records, format);
+    }
 
     let (name_len, ip_len, org_len, mm_len, hs_len, sec_len, cc_len, rop_len) =
         get_longest_stuff(nuked_machines.iter().map(|m| &**m.0));
@@ -307,7 +802,7 @@ fn nuke_mode(
                 write!(&mut print_str, "\n ").unwrap();
             },
 
-            Name => {
+            Name | Tree => {
                 write!(
                     &mut print_str,
                     "{: <lnl$}   ",
@@ -331,7 +826,7 @@ fn nuke_mode(
         let nuke_mode = match status {
             WasNuked => "nuked",
             JustNuked => "NUKED",
-            NotNuked => "     ",
+            NotNuked | MissingOpeners(_) => "     ",
         };
 
         writeln!(
@@ -360,30 +855,256 @@ fn nuke_mode(
             lrop = rop_len,
         )
         .unwrap();
+
+        if let MissingOpeners(missing) = status {
+            let missing = missing
+                .iter()
+                .map(|o| o.filename())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            // replace the newline so the programs end the machine's line
+            print_str.pop();
+            writeln!(&mut print_str, "   needs {}", missing).unwrap();
+        }
+    }
+
+    print_str
+}
+
+/// Summarises which port openers to buy next, and how much money each one
+/// unlocks.
+fn plan_mode(
+    ns: &NsWrapper,
+    network: &[ScannedMachine],
+) -> String {
+    let openers = get_available_openers(ns);
+    let hacking_level = ns.get_player_hacking_level();
+
+    let unrooted = network
+        .iter()
+        .filter(|m| !m.is_player_owned() && !m.is_root(ns))
+        .collect::<Vec<_>>();
+
+    let locked = unrooted
+        .iter()
+        .filter(|m| m.get_min_hacking_skill() <= hacking_level)
+        .map(|m| (m.get_required_open_ports(), m.get_max_money()))
+        .collect::<Vec<_>>();
+
+    let mut print_str = format!(
+        "\nPort openers on home: {}/{}\n",
+        openers.len(),
+        PortOpener::ALL.len()
+    );
+
+    let steps = plan_purchases(&openers, &locked);
+    if steps.is_empty() {
+        print_str += "Every port opener has been bought.\n";
+    }
+
+    let nukable = locked
+        .iter()
+        .filter(|(ports, _)| *ports <= openers.len())
+        .count();
+    if 0 < nukable {
+        writeln!(
+            &mut print_str,
+            "{} machines can be nuked already, run --exec nuke.",
+            nukable
+        )
+        .unwrap();
+    }
+
+    let mut total_cost = 0.;
+    for step in steps.iter() {
+        total_cost += step.opener.cost();
+
+        writeln!(
+            &mut print_str,
+            "buy {: <13}  {:.3e} total   unlocks {: >2} machines   ${:.3e}",
+            step.opener.filename(),
+            total_cost,
+            step.machines,
+            step.money as f64,
+        )
+        .unwrap();
+    }
+
+    let skill_locked = unrooted.len() - locked.len();
+    if 0 < skill_locked {
+        writeln!(
+            &mut print_str,
+            "{} more machines need a higher hacking level.",
+            skill_locked
+        )
+        .unwrap();
     }
 
-    ns.tprint(&*print_str);
+    print_str
+}
+
+/// Files that can be copied back to home.
+const FETCHED_EXTENSIONS: [&str; 3] = [".lit", ".txt", ".script"];
+
+/// Files that can be read as text.
+const TEXT_EXTENSIONS: [&str; 2] = [".txt", ".script"];
+
+fn has_extension(
+    filename: &str,
+    extensions: &[&str],
+) -> bool {
+    extensions.iter().any(|extension| filename.ends_with(extension))
+}
+
+/// Returns the contents of a text file on another machine, or why it could
+/// not be read.
+///
+/// Scripts can only read files on their own host, so the file is copied there
+/// for the time it is read. Files of the same name already on the host are
+/// never overwritten, the remote file is left unread instead.
+fn read_remote_file(
+    ns: &NsWrapper,
+    hostname: &str,
+    filename: &str,
+) -> Result<String, String> {
+    let current_hostname = ns.get_hostname();
+
+    if hostname == current_hostname {
+        return Ok(ns.read(filename));
+    }
+
+    if ns.file_exists(filename, &current_hostname) {
+        return Err(format!(
+            "{} already exists on {}",
+            filename, current_hostname
+        ));
+    }
+
+    if !ns.scp(filename, &current_hostname, hostname) {
+        return Err(format!("unable to copy {}", filename));
+    }
+
+    let contents = ns.read(filename);
+    ns.rm(filename, Some(&current_hostname));
+
+    Ok(contents)
+}
+
+/// Copies a file to the current host and returns where it ended up, or why
+/// it could not be copied. Text files go in a folder named after their host,
+/// while literature files can't be written by scripts so they are copied as
+/// they are, unless a file of the same name is already there.
+fn fetch_file(
+    ns: &NsWrapper,
+    hostname: &str,
+    filename: &str,
+    contents: Option<&str>,
+) -> Result<String, String> {
+    if !has_extension(filename, &TEXT_EXTENSIONS) {
+        let current_hostname = ns.get_hostname();
+
+        if ns.file_exists(filename, &current_hostname) {
+            return Err(format!(
+                "{} already exists on {}",
+                filename, current_hostname
+            ));
+        }
+
+        return match ns.scp(filename, &current_hostname, hostname) {
+            true => Ok(filename.to_owned()),
+            false => Err(format!("unable to copy {}", filename)),
+        };
+    }
+
+    let destination = format!("{}/{}", hostname, filename);
+    let contents = match contents {
+        Some(contents) => contents.to_owned(),
+        None => read_remote_file(ns, hostname, filename)?,
+    };
+
+    ns.write(&destination, &contents, 'w');
+    Ok(destination)
 }
 
 fn sniff_mode(
     ns: &NsWrapper,
-    network: &mut [ScannedMachine],
+    network: &[ScannedMachine],
     display_mode: DisplayMode,
-) {
+    format: OutputFormat,
+    options: &SniffOptions,
+) -> String {
     use DisplayMode::*;
 
     let mut print_str = "\n".to_owned();
+    let mut records = vec![];
+
     for machine in network.iter() {
         if machine.is_player_owned() {
             continue;
         }
 
-        let files = ns.ls(machine.get_hostname());
+        let hostname = machine.get_hostname();
+        let files = ns
+            .ls(hostname)
+            .into_iter()
+            .filter(|filename| {
+                options.globs.is_empty() ||
+                    options.globs.iter().any(|g| glob_match(g, filename))
+            })
+            .collect::<Vec<_>>();
 
         if files.is_empty() {
             continue;
         }
 
+        let mut fetched = vec![];
+        let mut contents = vec![];
+
+        for filename in files.iter() {
+            let is_text = has_extension(filename, &TEXT_EXTENSIONS);
+            let text = match options.cat && is_text {
+                true => Some(read_remote_file(ns, hostname, filename)),
+                false => None,
+            };
+
+            let is_fetched = has_extension(filename, &FETCHED_EXTENSIONS);
+            let destination = match options.fetch && is_fetched {
+                true => {
+                    let text = text.as_ref().and_then(|t| t.as_deref().ok());
+                    Some(fetch_file(ns, hostname, filename, text))
+                },
+                false => None,
+            };
+
+            fetched.push(destination);
+            contents.push(text);
+        }
+
+        if format != OutputFormat::Table {
+            let fetched = fetched
+                .into_iter()
+                .flatten()
+                .filter_map(Result::ok)
+                .collect();
+            let contents = files
+                .iter()
+                .zip(contents)
+                .filter_map(|(filename, text)| {
+                    Some((filename.clone(), text?.ok()?))
+                })
+                .collect();
+
+            records.push(MachineRecord {
+                fetched: options.fetch.then_some(fetched),
+                contents: options.cat.then_some(contents),
+                files: Some(files),
+                ..MachineRecord::new(ns, machine)
+            });
+
+            continue;
+        }
+
         match display_mode {
             Path => {
                 write!(&mut print_str, "\n").unwrap();
@@ -401,42 +1122,77 @@ fn sniff_mode(
                 write!(&mut print_str, "\n").unwrap();
             },
 
-            Name => {
+            Name | Tree => {
                 writeln!(&mut print_str, "\n{}", machine.get_hostname())
                     .unwrap();
             },
         }
 
-        for filename in files.into_iter() {
-            writeln!(&mut print_str, "- {}", filename).unwrap();
+        for ((filename, fetched), text) in
+            files.iter().zip(fetched.iter()).zip(contents.iter())
+        {
+            write!(&mut print_str, "- {}", filename).unwrap();
+            match fetched {
+                Some(Ok(destination)) => {
+                    write!(&mut print_str, "  -> {}", destination).unwrap()
+                },
+                Some(Err(e)) => write!(&mut print_str, "  ({})", e).unwrap(),
+                None => (),
+            }
+            writeln!(&mut print_str).unwrap();
+
+            match text {
+                Some(Ok(text)) => {
+                    for line in text.lines() {
+                        writeln!(&mut print_str, "    {}", line).unwrap();
+                    }
+                },
+                Some(Err(e)) => {
+                    writeln!(&mut print_str, "    ({})", e).unwrap();
+                },
+                None => (),
+            }
         }
     }
 
-    ns.tprint(&*print_str);
+    if format != OutputFormat::Table {
+        return format_records(&records, format);
+    }
+
+    print_str
 }
 
-fn backdoor_mode(
+/// Returns the machines we can backdoor, faction servers first.
+fn get_backdoor_candidates<'a>(
     ns: &NsWrapper,
-    network: &[ScannedMachine],
-) {
-    let mut print_str = "\n".to_owned();
-    for machine in network.iter() {
-        if machine.is_player_owned() {
-            continue;
-        }
+    network: &'a [ScannedMachine],
+) -> Vec<&'a ScannedMachine> {
+    let hacking_level = ns.get_player_hacking_level();
 
-        if machine.is_backdoored(ns) {
-            continue;
-        }
+    let mut candidates = network
+        .iter()
+        .filter(|m| !m.is_player_owned())
+        .filter(|m| m.get_min_hacking_skill() <= hacking_level)
+        .filter(|m| m.is_root(ns) && !m.is_backdoored(ns))
+        .collect::<Vec<_>>();
 
-        if ns.get_player_hacking_level() < machine.get_min_hacking_skill() {
-            continue;
-        }
+    // the sort is stable, so the other machines keep their order
+    candidates.sort_by_key(|m| {
+        FACTION_SERVERS
+            .iter()
+            .position(|hostname| *hostname == m.get_hostname())
+            .unwrap_or(FACTION_SERVERS.len())
+    });
 
-        if !machine.is_root(ns) {
-            continue;
-        }
+    candidates
+}
 
+fn backdoor_mode(
+    ns: &NsWrapper,
+    network: &[ScannedMachine],
+) -> String {
+    let mut print_str = "\n".to_owned();
+    for machine in get_backdoor_candidates(ns, network) {
         write!(&mut print_str, "\nhome; ").unwrap();
         for traversal in machine.get_traversal().iter().skip(1) {
             write!(&mut print_str, "connect {}; ", traversal).unwrap();
@@ -445,9 +1201,181 @@ fn backdoor_mode(
     }
 
     if print_str == "\n" {
-        ns.tprint("No machines to backdoor.");
+        "No machines to backdoor.".to_owned()
+    }
+    else {
+        print_str
+    }
+}
+
+/// Walks to each machine we can backdoor, installs the backdoor then goes
+/// back home.
+async fn auto_backdoor_mode(
+    ns: &NsWrapper<'_>,
+    network: &[ScannedMachine],
+) -> String {
+    let mut print_str = "\n".to_owned();
+    for machine in get_backdoor_candidates(ns, network) {
+        // every traversal starts from home
+        ns.singularity_connect("home");
+
+        let is_reached = machine
+            .get_traversal()
+            .iter()
+            .skip(1)
+            .all(|hostname| ns.singularity_connect(hostname));
+
+        if !is_reached {
+            writeln!(
+                &mut print_str,
+                "{}: unable to connect, stopped at {}",
+                machine.get_hostname(),
+                ns.singularity_get_current_server()
+            )
+            .unwrap();
+        }
+        else {
+            match ns.singularity_install_backdoor().await {
+                Ok(()) => {
+                    writeln!(
+                        &mut print_str,
+                        "{}: backdoored",
+                        machine.get_hostname()
+                    )
+                    .unwrap();
+                },
+                Err(e) => {
+                    writeln!(
+                        &mut print_str,
+                        "{}: unable to backdoor: {:?}",
+                        machine.get_hostname(),
+                        e
+                    )
+                    .unwrap();
+                },
+            }
+        }
+    }
+
+    ns.singularity_connect("home");
+
+    if print_str == "\n" {
+        "No machines to backdoor.".to_owned()
     }
     else {
-        ns.tprint(&*print_str);
+        print_str
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use clap::Parser;
+
+    use super::{
+        format_records,
+        MachineRecord,
+        OutputFormat,
+        ScanMode,
+    };
+
+    #[derive(Parser)]
+    struct ScanCommand {
+        #[command(flatten)]
+        scan: ScanMode,
+    }
+
+    fn make_record(hostname: &str) -> MachineRecord {
+        MachineRecord {
+            hostname: hostname.to_owned(),
+            traversal: vec!["home".to_owned(), hostname.to_owned()],
+            degree: 1,
+            ip_address: "1.2.3.4".to_owned(),
+            organization: "Foo, Inc.".to_owned(),
+            max_money: 1000,
+            hacking_skill: 5,
+            min_security: 1.5,
+            cpu_cores: 1,
+            required_open_ports: 0,
+            root: true,
+            backdoor: false,
+            missing_openers: None,
+            files: None,
+            fetched: None,
+            contents: None,
+        }
+    }
+
+    #[test]
+    fn csv_output() {
+        let csv = format_records(&[make_record("n00dles")], OutputFormat::Csv);
+        let mut lines = csv.lines();
+
+        assert_eq!(
+            lines.next().unwrap().split(',').collect::<Vec<_>>().len(),
+            12
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "n00dles,home/n00dles,1,1.2.3.4,\"Foo, Inc.\",1000,5,1.5,1,0,true,\
+             false"
+        );
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn json_output() {
+        let mut record = make_record("n00dles");
+        let json = format_records(&[record.clone()], OutputFormat::Json);
+        assert!(!json.contains("files"));
+
+        record.files = Some(vec!["a.lit".to_owned()]);
+        let json = format_records(&[record], OutputFormat::Json);
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0]["files"][0], "a.lit");
+        assert_eq!(parsed[0]["max_money"], 1000);
+    }
+
+    #[test]
+    fn sniffed_files_output() {
+        let records = [MachineRecord {
+            files: Some(vec!["a.lit".to_owned(), "b.txt".to_owned()]),
+            fetched: Some(vec!["a.lit".to_owned()]),
+            contents: Some(BTreeMap::from([(
+                "b.txt".to_owned(),
+                "hi\n".to_owned(),
+            )])),
+            ..make_record("n00dles")
+        }];
+
+        let json = format_records(&records, OutputFormat::Json);
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0]["fetched"][0], "a.lit");
+        assert_eq!(parsed[0]["contents"]["b.txt"], "hi\n");
+
+        let csv = format_records(&records, OutputFormat::Csv);
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().ends_with(",files,fetched,contents"));
+        assert!(lines
+            .next()
+            .unwrap()
+            .ends_with(r#",a.lit;b.txt,a.lit,"{""b.txt"":""hi\n""}""#));
+    }
+
+    #[test]
+    fn watch_runs_alone() {
+        let parse = |args: &[&str]| {
+            ScanCommand::try_parse_from(["scan"].iter().chain(args))
+        };
+
+        let command = parse(&["--watch", "2.5"]).unwrap();
+        assert_eq!(command.scan.watch, Some(2.5));
+
+        assert!(parse(&["--watch", "0"]).is_err());
+        assert!(parse(&["--watch", "1", "--exec", "nuke"]).is_err());
+        assert!(parse(&["--watch", "1", "--export", "dot"]).is_err());
+        assert!(parse(&["--refreshes", "3"]).is_err());
+        assert!(parse(&["--watch", "1", "--watch-for", "-1"]).is_err());
     }
 }
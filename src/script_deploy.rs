@@ -1,34 +1,149 @@
+use std::collections::HashMap;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
 use crate::{
     machine::Machine,
     netscript::NsWrapper,
 };
 
+/// File on the current host recording which version of each file was
+/// deployed to each host.
+const DEPLOY_MANIFEST_FILE: &str = "deploy_manifest.txt";
+
 pub struct DynamicFile<'a> {
     pub filename: &'a str,
     pub contents: &'a str,
 }
 
 impl<'a> DynamicFile<'a> {
-    pub fn deploy_to_machine(
+    /// Returns a hash of the contents, stable across builds.
+    pub fn get_content_hash(&self) -> u64 {
+        // FNV-1a, since the std hashers may change between releases
+        self.contents
+            .bytes()
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+}
+
+/// The hashes of the files deployed to each host, keyed by hostname then
+/// filename.
+///
+/// It is loaded once, passed to every deployment, then saved when they are
+/// done.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeployManifest {
+    hosts: HashMap<String, HashMap<String, u64>>,
+}
+
+impl DeployManifest {
+    pub fn load(ns: &NsWrapper<'_>) -> DeployManifest {
+        serde_json::from_str(&ns.read(DEPLOY_MANIFEST_FILE)).unwrap_or_default()
+    }
+
+    pub fn save(
         &self,
         ns: &NsWrapper<'_>,
-        machine: &Machine,
-        force: bool,
-    ) -> bool {
-        if !force && ns.file_exists(self.filename, machine.get_hostname()) {
-            return true;
+    ) {
+        ns.write(
+            DEPLOY_MANIFEST_FILE,
+            &serde_json::to_string(self).unwrap(),
+            'w',
+        );
+    }
+
+    /// Returns the files that have to be copied to the host, either because
+    /// they are missing there or because they changed since.
+    fn get_stale_files<'b, 'c>(
+        &self,
+        hostname: &str,
+        files: &[&'b DynamicFile<'c>],
+        exists_on_host: impl Fn(&str) -> bool,
+    ) -> Vec<&'b DynamicFile<'c>> {
+        let deployed = self.hosts.get(hostname);
+
+        files
+            .iter()
+            .filter(|file| {
+                let hash = deployed.and_then(|d| d.get(file.filename));
+                hash != Some(&file.get_content_hash()) ||
+                    !exists_on_host(file.filename)
+            })
+            .copied()
+            .collect()
+    }
+
+    fn record(
+        &mut self,
+        hostname: &str,
+        files: &[&DynamicFile<'_>],
+    ) {
+        let deployed = self.hosts.entry(hostname.to_owned()).or_default();
+
+        for file in files {
+            deployed.insert(file.filename.to_owned(), file.get_content_hash());
         }
+    }
+}
 
-        let current_hostname = ns.get_hostname();
-        ns.write(self.filename, self.contents, 'w');
-        ns.scp(
-            "child_weaken.js",
-            machine.get_hostname(),
-            &*current_hostname,
-        );
+/// Copies the files that are missing or outdated on the machine in a single
+/// `scp`. Returns true if every file is up to date on the machine afterwards.
+///
+/// With `force`, every file is copied regardless of what is already there.
+/// The copies are recorded in `manifest`, which is left to the caller to
+/// save.
+pub fn deploy_files(
+    ns: &NsWrapper<'_>,
+    manifest: &mut DeployManifest,
+    files: &[&DynamicFile<'_>],
+    machine: &Machine,
+    force: bool,
+) -> bool {
+    let hostname = machine.get_hostname();
+
+    let stale = match force {
+        true => files.to_vec(),
+        false => manifest.get_stale_files(hostname, files, |filename| {
+            ns.file_exists(filename, hostname)
+        }),
+    };
+
+    if stale.is_empty() {
+        return true;
+    }
+
+    // the files have to be on the current host before they can be copied
+    let current_hostname = ns.get_hostname();
+    for file in stale.iter() {
+        if ns.read(file.filename) != file.contents {
+            ns.write(file.filename, file.contents, 'w');
+        }
+    }
 
-        ns.file_exists(self.filename, machine.get_hostname())
+    if hostname != current_hostname {
+        let filenames = stale.iter().map(|f| f.filename).collect::<Vec<_>>();
+        if !ns.scp_many(&filenames, hostname, &current_hostname) {
+            return false;
+        }
     }
+
+    manifest.record(hostname, &stale);
+
+    true
+}
+
+/// Copies the HGW scripts onto the machine.
+pub fn deploy_child_scripts(
+    ns: &NsWrapper<'_>,
+    manifest: &mut DeployManifest,
+    machine: &Machine,
+) -> bool {
+    deploy_files(ns, manifest, &CHILD_SCRIPTS, machine, false)
 }
 
 pub const WEAKEN_SCRIPT: DynamicFile<'static> = DynamicFile {
@@ -46,6 +161,11 @@ pub const GROW_SCRIPT: DynamicFile<'static> = DynamicFile {
     contents: include_str!("child_grow.js"),
 };
 
+pub const CHILD_SCRIPTS: [&DynamicFile<'static>; 3] =
+    [&HACK_SCRIPT, &GROW_SCRIPT, &WEAKEN_SCRIPT];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum HGW {
     Hack,
     Weaken,
@@ -63,3 +183,158 @@ impl HGW {
         }
     }
 }
+
+/// Options of `ns.hack`, `ns.grow` and `ns.weaken` that the child scripts
+/// pass along.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HGWOptions {
+    /// Milliseconds to wait before the action starts. The duration of the
+    /// action itself is fixed when the child script is launched.
+    pub additional_msec: f64,
+    /// Number of threads the action uses. Zero uses all of them.
+    pub threads: usize,
+    /// Whether the action should influence the stock of the target.
+    pub stock: bool,
+}
+
+impl HGWOptions {
+    /// Returns the child script arguments for these options.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--delay".to_owned(),
+            format!("{}", self.additional_msec.max(0.).round()),
+        ];
+
+        if 0 < self.threads {
+            args.push("--threads".to_owned());
+            args.push(format!("{}", self.threads));
+        }
+
+        if self.stock {
+            args.push("--stock".to_owned());
+        }
+
+        args
+    }
+}
+
+/// What a child script writes to its report port once its action has landed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChildReport {
+    pub pid: usize,
+    /// Batch the child script was spawned for. Zero if untagged.
+    #[serde(default)]
+    pub batch: u64,
+    /// Position of the action inside its batch.
+    #[serde(default)]
+    pub index: usize,
+    pub action: HGW,
+    pub target: String,
+    /// Time the action landed, in milliseconds since the epoch.
+    pub landed: f64,
+    /// Return value of the action: the money stolen by a hack, the
+    /// multiplier of a grow and the security decrease of a weaken.
+    pub result: f64,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ChildReport,
+        DeployManifest,
+        HGWOptions,
+        CHILD_SCRIPTS,
+        GROW_SCRIPT,
+        HACK_SCRIPT,
+        HGW,
+    };
+
+    #[test]
+    fn content_hash_is_stable() {
+        let file = super::DynamicFile {
+            filename: "a.js",
+            contents: "a",
+        };
+        assert_eq!(file.get_content_hash(), 0xaf63dc4c8601ec8c);
+        assert_ne!(
+            HACK_SCRIPT.get_content_hash(),
+            GROW_SCRIPT.get_content_hash()
+        );
+    }
+
+    #[test]
+    fn manifest_finds_stale_files() {
+        let mut manifest = DeployManifest::default();
+
+        // nothing was deployed yet
+        let stale =
+            manifest.get_stale_files("n00dles", &CHILD_SCRIPTS, |_| true);
+        assert_eq!(stale.len(), 3);
+
+        manifest.record("n00dles", &CHILD_SCRIPTS);
+        let stale =
+            manifest.get_stale_files("n00dles", &CHILD_SCRIPTS, |_| true);
+        assert!(stale.is_empty());
+
+        // the file was deleted on the host
+        let stale = manifest.get_stale_files("n00dles", &CHILD_SCRIPTS, |f| {
+            f != HACK_SCRIPT.filename
+        });
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].filename, HACK_SCRIPT.filename);
+
+        // the file changed since
+        manifest
+            .hosts
+            .get_mut("n00dles")
+            .unwrap()
+            .insert(GROW_SCRIPT.filename.to_owned(), 0);
+        let stale =
+            manifest.get_stale_files("n00dles", &CHILD_SCRIPTS, |_| true);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].filename, GROW_SCRIPT.filename);
+    }
+
+    #[test]
+    fn hgw_options_args() {
+        let options = HGWOptions {
+            additional_msec: 1234.6,
+            threads: 0,
+            stock: false,
+        };
+        assert_eq!(options.args(), ["--delay", "1235"]);
+
+        let options = HGWOptions {
+            additional_msec: -5.,
+            threads: 12,
+            stock: true,
+        };
+        assert_eq!(
+            options.args(),
+            ["--delay", "0", "--threads", "12", "--stock"]
+        );
+    }
+
+    #[test]
+    fn parse_child_report() {
+        let report: ChildReport = serde_json::from_str(
+            r#"{
+                "pid": 42,
+                "batch": 7,
+                "index": 2,
+                "action": "hack",
+                "target": "n00dles",
+                "landed": 1.5e12,
+                "result": 1234.5
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(report.pid, 42);
+        assert_eq!(report.batch, 7);
+        assert_eq!(report.index, 2);
+        assert_eq!(report.action, HGW::Hack);
+        assert_eq!(report.target, "n00dles");
+        assert_eq!(report.result, 1234.5);
+    }
+}
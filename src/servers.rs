@@ -0,0 +1,292 @@
+use std::fmt::Write as _;
+
+use clap::Args;
+
+use crate::{
+    machine::Machine,
+    netscript::NsWrapper,
+    script_deploy::{
+        deploy_child_scripts,
+        DeployManifest,
+        HGW,
+    },
+};
+
+/// Prefix of the names given to the servers we buy.
+pub const SERVER_NAME_PREFIX: &str = "pserv-";
+
+/// Smallest amount of RAM a purchased server can have, in GB.
+const MIN_SERVER_RAM: f64 = 2.;
+
+#[derive(Args, Debug, Clone)]
+pub struct ServersMode {
+    #[command(flatten)]
+    budget: ServerBudget,
+    #[arg(
+        long,
+        value_name = "HOSTNAME",
+        help = "kill the child scripts on a purchased server then delete it"
+    )]
+    delete: Option<String>,
+}
+
+impl ServersMode {
+    pub fn execute(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) {
+        if let Some(hostname) = &self.delete {
+            delete_server(ns, hostname);
+            return;
+        }
+
+        let changes = self.budget.manage_servers(ns);
+
+        if changes.is_empty() {
+            ns.tprint("Nothing was bought or upgraded.");
+            return;
+        }
+
+        let mut print_str = "\n".to_owned();
+        for change in changes.iter() {
+            writeln!(&mut print_str, "{}", change).unwrap();
+        }
+
+        ns.tprint(&print_str);
+    }
+}
+
+// how much of our money can be spent on purchased servers. this is not a
+// doc comment since clap would show it as the about of the subcommands it
+// is flattened into.
+#[derive(Args, Debug, Clone)]
+pub struct ServerBudget {
+    #[arg(
+        long,
+        default_value_t = 0.5,
+        help = "fraction of the money on home spent on servers"
+    )]
+    spend_fraction: f64,
+    #[arg(long, help = "most money spent on servers at once")]
+    max_spend: Option<f64>,
+    #[arg(
+        long,
+        default_value_t = 8.,
+        help = "smallest server bought, in GB of RAM"
+    )]
+    min_ram: f64,
+}
+
+/// Something that was bought by the server manager.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerChange {
+    Purchased { hostname: String, ram: f64, cost: f64 },
+    Upgraded { hostname: String, ram: f64, cost: f64 },
+}
+
+impl std::fmt::Display for ServerChange {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        let (hostname, verb, ram, cost) = match self {
+            ServerChange::Purchased {
+                hostname,
+                ram,
+                cost,
+            } => (hostname, "bought", ram, cost),
+            ServerChange::Upgraded {
+                hostname,
+                ram,
+                cost,
+            } => (hostname, "upgraded to", ram, cost),
+        };
+
+        write!(f, "{}: {} {} GB for {:.3e}", hostname, verb, ram, cost)
+    }
+}
+
+impl ServerBudget {
+    fn get_budget(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) -> f64 {
+        let money = ns.get_server_money_available("home").unwrap() as f64;
+        let budget = money * self.spend_fraction.clamp(0., 1.);
+
+        match self.max_spend {
+            Some(max_spend) => budget.min(max_spend),
+            None => budget,
+        }
+    }
+
+    /// Buys the largest servers we can afford until we hit the limit, then
+    /// upgrades the smallest ones with whatever is left of the budget.
+    pub fn manage_servers(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) -> Vec<ServerChange> {
+        let mut budget = self.get_budget(ns);
+        let mut changes = vec![];
+
+        let max_ram = ns.get_purchased_server_max_ram();
+        let limit = ns.get_purchased_server_limit();
+        let mut owned = ns.get_purchased_servers();
+        let mut manifest = DeployManifest::load(ns);
+
+        while owned.len() < limit {
+            let Some(ram) = get_largest_affordable_ram(
+                budget,
+                self.min_ram,
+                max_ram,
+                |ram| ns.get_purchased_server_cost(ram),
+            )
+            else {
+                break;
+            };
+
+            let cost = ns.get_purchased_server_cost(ram);
+            let name = get_next_server_name(&owned);
+
+            let Some(hostname) = ns.purchase_server(&name, ram)
+            else {
+                break;
+            };
+
+            budget -= cost;
+            let machine =
+                Machine::home(ns).create_child(ns, hostname.clone());
+            deploy_child_scripts(ns, &mut manifest, &machine);
+            owned.push(hostname.clone());
+            changes.push(ServerChange::Purchased {
+                hostname,
+                ram,
+                cost,
+            });
+        }
+
+        if !changes.is_empty() {
+            manifest.save(ns);
+        }
+
+        let mut servers = owned
+            .into_iter()
+            .map(|hostname| {
+                let ram = ns.get_server_max_ram(&hostname);
+                (hostname, ram)
+            })
+            .collect::<Vec<_>>();
+
+        // double the smallest server until we run out of money, so that the
+        // servers stay about the same size
+        while let Some((hostname, ram)) = servers
+            .iter_mut()
+            .filter(|(_, ram)| *ram < max_ram)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        {
+            let new_ram = (*ram * 2.).min(max_ram);
+            let cost = ns.get_purchased_server_upgrade_cost(hostname, new_ram);
+
+            if budget < cost || !ns.upgrade_purchased_server(hostname, new_ram)
+            {
+                break;
+            }
+
+            budget -= cost;
+            *ram = new_ram;
+            changes.push(ServerChange::Upgraded {
+                hostname: hostname.clone(),
+                ram: new_ram,
+                cost,
+            });
+        }
+
+        changes
+    }
+}
+
+fn delete_server(
+    ns: &NsWrapper<'_>,
+    hostname: &str,
+) {
+    if !ns.get_purchased_servers().iter().any(|s| s == hostname) {
+        ns.tprint(&format!("{} is not a purchased server.", hostname));
+        return;
+    }
+
+    for hgw in [HGW::Hack, HGW::Grow, HGW::Weaken] {
+        ns.script_kill(hgw.script().filename, hostname);
+    }
+
+    match ns.delete_server(hostname) {
+        true => ns.tprint(&format!("Deleted {}.", hostname)),
+        false => ns.tprint(&format!(
+            "Unable to delete {}. Are other scripts running on it?",
+            hostname
+        )),
+    }
+}
+
+/// Returns the largest power of two amount of RAM, between `min_ram` and
+/// `max_ram`, whose server we can afford.
+fn get_largest_affordable_ram(
+    budget: f64,
+    min_ram: f64,
+    max_ram: f64,
+    get_cost: impl Fn(f64) -> f64,
+) -> Option<f64> {
+    let mut ram = MIN_SERVER_RAM;
+    let mut largest = None;
+
+    while ram <= max_ram {
+        if min_ram <= ram {
+            if budget < get_cost(ram) {
+                break;
+            }
+
+            largest = Some(ram);
+        }
+
+        ram *= 2.;
+    }
+
+    largest
+}
+
+/// Returns the first name of the form `pserv-<n>` that is not taken yet.
+fn get_next_server_name(taken: &[String]) -> String {
+    (0..)
+        .map(|i| format!("{}{}", SERVER_NAME_PREFIX, i))
+        .find(|name| !taken.contains(name))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        get_largest_affordable_ram,
+        get_next_server_name,
+    };
+
+    #[test]
+    fn largest_affordable_ram() {
+        let cost = |ram: f64| ram * 55_000.;
+
+        assert_eq!(
+            get_largest_affordable_ram(1e6, 8., 1024., cost),
+            Some(16.)
+        );
+        assert_eq!(get_largest_affordable_ram(1e5, 8., 1024., cost), None);
+        assert_eq!(
+            get_largest_affordable_ram(1e12, 8., 1024., cost),
+            Some(1024.)
+        );
+    }
+
+    #[test]
+    fn server_names_fill_gaps() {
+        let taken = vec!["pserv-0".to_owned(), "pserv-2".to_owned()];
+        assert_eq!(get_next_server_name(&taken), "pserv-1");
+        assert_eq!(get_next_server_name(&[]), "pserv-0");
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use crate::stock::{
+    forecast::TrendEstimator,
+    trader::{
+        get_expected_return,
+        get_shares_to_buy,
+        TradeRules,
+        COMMISSION,
+    },
+    Position,
+    StockBias,
+};
+
+/// Prices of every stock on every market tick, as recorded by the trader.
+///
+/// Stored as CSV: the first line holds the symbols, then there is one line of
+/// prices per tick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickSeries {
+    pub symbols: Vec<String>,
+    pub ticks: Vec<Vec<f64>>,
+}
+
+impl TickSeries {
+    pub fn parse(text: &str) -> Result<TickSeries, String> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+
+        let symbols = lines
+            .next()
+            .ok_or("the tick series is empty")?
+            .split(',')
+            .map(|symbol| symbol.trim().to_owned())
+            .collect::<Vec<_>>();
+
+        let mut ticks = Vec::new();
+
+        for (i, line) in lines.enumerate() {
+            let prices = line
+                .split(',')
+                .map(|price| price.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("tick {}: {}", i, e))?;
+
+            if prices.len() != symbols.len() {
+                return Err(format!(
+                    "tick {}: expected {} prices, got {}",
+                    i,
+                    symbols.len(),
+                    prices.len()
+                ));
+            }
+
+            ticks.push(prices);
+        }
+
+        Ok(TickSeries { symbols, ticks })
+    }
+
+    pub fn header(symbols: &[String]) -> String {
+        symbols.join(",") + "\n"
+    }
+
+    pub fn row(prices: &[f64]) -> String {
+        let row = prices
+            .iter()
+            .map(|price| price.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        row + "\n"
+    }
+}
+
+/// Money we get back by closing a position at `price`.
+fn get_position_value(
+    position: &Position,
+    price: f64,
+) -> f64 {
+    position.long_shares * price +
+        position.short_shares * (2. * position.short_average_price - price)
+}
+
+/// Adds the shares bought at `price` to one side of a position.
+fn add_shares(
+    shares: &mut f64,
+    average_price: &mut f64,
+    bought: f64,
+    price: f64,
+) {
+    *average_price =
+        (*shares * *average_price + bought * price) / (*shares + bought);
+    *shares += bought;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BacktestResult {
+    pub starting_money: f64,
+    /// Money plus the value of the positions still open at the end.
+    pub final_value: f64,
+    pub trades: usize,
+    pub flips_detected: u32,
+}
+
+impl BacktestResult {
+    pub fn profit(&self) -> f64 {
+        self.final_value - self.starting_money
+    }
+}
+
+/// Replays a tick series through the price history trader, using the same
+/// decisions as the live trader. Trades happen at the recorded prices,
+/// without spread or market impact.
+pub fn run_backtest(
+    series: &TickSeries,
+    rules: &TradeRules,
+    starting_money: f64,
+) -> BacktestResult {
+    let mut estimator = TrendEstimator::default();
+    let mut positions = HashMap::<&str, Position>::new();
+    let mut money = starting_money;
+    let mut trades = 0;
+
+    for prices in series.ticks.iter() {
+        for (symbol, price) in series.symbols.iter().zip(prices.iter()) {
+            estimator.record(symbol, *price);
+        }
+        estimator.next_tick();
+
+        let is_flip_near = estimator.is_flip_near();
+        let mut candidates = Vec::new();
+
+        for (symbol, price) in series.symbols.iter().zip(prices.iter()) {
+            let estimate =
+                estimator.forecast(symbol).zip(estimator.volatility(symbol));
+
+            let Some((forecast, volatility)) = estimate
+            else {
+                continue;
+            };
+
+            let position = positions.entry(symbol.as_str()).or_default();
+            let decision = rules.decide(forecast, position, is_flip_near);
+
+            if decision.close_long {
+                money += position.long_shares * price - COMMISSION;
+                position.long_shares = 0.;
+                trades += 1;
+            }
+
+            if decision.close_short {
+                money += position.short_shares *
+                    (2. * position.short_average_price - price) -
+                    COMMISSION;
+                position.short_shares = 0.;
+                trades += 1;
+            }
+
+            if decision.open != StockBias::Neutral {
+                let expected_return = get_expected_return(forecast, volatility);
+                candidates.push((
+                    symbol.as_str(),
+                    *price,
+                    decision.open,
+                    expected_return,
+                ));
+            }
+        }
+
+        candidates.sort_by(|a, b| b.3.abs().total_cmp(&a.3.abs()));
+
+        for (symbol, price, side, _) in candidates {
+            let budget = (money - COMMISSION).max(0.);
+            let shares = get_shares_to_buy(budget, price, f64::INFINITY);
+
+            if shares <= 0. {
+                continue;
+            }
+
+            let position = positions.get_mut(symbol).unwrap();
+            match side {
+                StockBias::Long => add_shares(
+                    &mut position.long_shares,
+                    &mut position.long_average_price,
+                    shares,
+                    price,
+                ),
+                StockBias::Short => add_shares(
+                    &mut position.short_shares,
+                    &mut position.short_average_price,
+                    shares,
+                    price,
+                ),
+                StockBias::Neutral => unreachable!(),
+            }
+
+            money -= shares * price + COMMISSION;
+            trades += 1;
+        }
+    }
+
+    let last_prices = series.ticks.last().cloned().unwrap_or_default();
+    let open_value = series
+        .symbols
+        .iter()
+        .zip(last_prices.iter())
+        .filter_map(|(symbol, price)| {
+            positions
+                .get(symbol.as_str())
+                .map(|position| get_position_value(position, *price))
+        })
+        .sum::<f64>();
+
+    BacktestResult {
+        starting_money,
+        final_value: money + open_value,
+        trades,
+        flips_detected: estimator.flips_detected(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        run_backtest,
+        TickSeries,
+        TradeRules,
+    };
+
+    #[test]
+    fn parse_round_trip() {
+        let symbols = vec!["ECP".to_owned(), "MGCP".to_owned()];
+        let text = TickSeries::header(&symbols) +
+            &TickSeries::row(&[1.5, 20.]) +
+            &TickSeries::row(&[1.25, 21.]);
+
+        let series = TickSeries::parse(&text).unwrap();
+        assert_eq!(series.symbols, symbols);
+        assert_eq!(series.ticks, vec![vec![1.5, 20.], vec![1.25, 21.]]);
+
+        assert!(TickSeries::parse("ECP,MGCP\n1.5\n").is_err());
+        assert!(TickSeries::parse("").is_err());
+    }
+
+    #[test]
+    fn rising_stock_makes_money() {
+        let mut price = 1000.;
+        let ticks = (0..100)
+            .map(|tick| {
+                price *= if tick % 4 == 0 { 0.99 } else { 1.01 };
+                vec![price]
+            })
+            .collect();
+
+        let series = TickSeries {
+            symbols: vec!["ECP".to_owned()],
+            ticks,
+        };
+
+        let rules = TradeRules {
+            buy_threshold: 0.6,
+            sell_threshold: 0.5,
+            short: false,
+        };
+
+        let result = run_backtest(&series, &rules, 1e9);
+        assert!(0. < result.profit(), "{:?}", result);
+        assert_eq!(result.trades, 1);
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::{
+    HashMap,
+    VecDeque,
+};
+
+/// Ticks between two chances for the forecasts of the stocks to flip. The
+/// phase of this cycle is hidden, so it has to be guessed from the flips.
+pub const MARKET_CYCLE_TICKS: u64 = 75;
+
+/// Price changes used to estimate the forecast of a stock.
+const LONG_WINDOW: usize = 50;
+/// Most recent price changes, compared against the older ones to spot a flip.
+const SHORT_WINDOW: usize = 10;
+/// Price changes needed before a forecast is estimated at all.
+const MIN_CHANGES: usize = SHORT_WINDOW * 2;
+/// How far from a coin toss both windows have to be, on opposite sides, to
+/// call it a flip.
+const FLIP_MARGIN: f64 = 0.15;
+/// Flips within this many ticks of each other are counted as the same phase.
+const FLIP_PHASE_TOLERANCE: u64 = 2;
+/// Flips that have to agree on a phase before it is trusted.
+const MIN_FLIP_VOTES: u32 = 3;
+/// Don't open positions this few ticks before an expected flip.
+const FLIP_GUARD_TICKS: u64 = 5;
+
+/// Recent prices of a single stock.
+#[derive(Debug, Clone, Default)]
+struct PriceHistory {
+    prices: VecDeque<f64>,
+}
+
+impl PriceHistory {
+    fn push(
+        &mut self,
+        price: f64,
+    ) {
+        self.prices.push_back(price);
+
+        while LONG_WINDOW + 1 < self.prices.len() {
+            self.prices.pop_front();
+        }
+    }
+
+    /// Relative change of the price on each tick, oldest first.
+    fn changes(&self) -> Vec<f64> {
+        self.prices
+            .iter()
+            .zip(self.prices.iter().skip(1))
+            .map(|(before, after)| (after - before) / before)
+            .collect()
+    }
+
+    /// Forgets everything but the prices of the last `changes` ticks.
+    fn keep_last(
+        &mut self,
+        changes: usize,
+    ) {
+        while changes + 1 < self.prices.len() {
+            self.prices.pop_front();
+        }
+    }
+}
+
+/// Fraction of the changes that went up. Unchanged prices are ignored.
+fn get_up_ratio(changes: &[f64]) -> Option<f64> {
+    let ups = changes.iter().filter(|change| 0. < **change).count();
+    let downs = changes.iter().filter(|change| **change < 0.).count();
+
+    (0 < ups + downs).then(|| ups as f64 / (ups + downs) as f64)
+}
+
+/// Estimates the forecasts of the stocks from their recent prices, for when
+/// we don't have the 4S market data.
+#[derive(Debug, Clone)]
+pub struct TrendEstimator {
+    tick: u64,
+    histories: HashMap<String, PriceHistory>,
+    /// How many flips were seen on each tick of the market cycle.
+    flip_votes: [u32; MARKET_CYCLE_TICKS as usize],
+}
+
+impl Default for TrendEstimator {
+    fn default() -> Self {
+        TrendEstimator {
+            tick: 0,
+            histories: HashMap::new(),
+            flip_votes: [0; MARKET_CYCLE_TICKS as usize],
+        }
+    }
+}
+
+impl TrendEstimator {
+    /// Records the price of a stock on the current tick.
+    pub fn record(
+        &mut self,
+        symbol: &str,
+        price: f64,
+    ) {
+        let history = self.histories.entry(symbol.to_owned()).or_default();
+        history.push(price);
+
+        let changes = history.changes();
+        if changes.len() < MIN_CHANGES {
+            return;
+        }
+
+        let (older, recent) = changes.split_at(changes.len() - SHORT_WINDOW);
+        let (Some(older), Some(recent)) =
+            (get_up_ratio(older), get_up_ratio(recent))
+        else {
+            return;
+        };
+
+        let is_flip = (older - 0.5) * (recent - 0.5) < 0. &&
+            FLIP_MARGIN <= (older - 0.5).abs() &&
+            FLIP_MARGIN <= (recent - 0.5).abs();
+
+        if is_flip {
+            // the older prices follow the old forecast, forget them
+            history.keep_last(SHORT_WINDOW);
+
+            // the flip happened somewhere in the short window
+            let phase = (self.tick + MARKET_CYCLE_TICKS -
+                SHORT_WINDOW as u64 / 2) %
+                MARKET_CYCLE_TICKS;
+            self.flip_votes[phase as usize] += 1;
+        }
+    }
+
+    /// Moves on to the next tick, once every stock had its price recorded.
+    /// This must only be called once per market tick, since the flip cycle is
+    /// counted in ticks rather than in time.
+    pub fn next_tick(&mut self) {
+        self.tick += 1;
+    }
+
+    /// Estimated probability of the price of a stock going up on the next
+    /// tick.
+    pub fn forecast(
+        &self,
+        symbol: &str,
+    ) -> Option<f64> {
+        let changes = self.histories.get(symbol)?.changes();
+
+        if changes.len() < SHORT_WINDOW {
+            return None;
+        }
+
+        get_up_ratio(&changes)
+    }
+
+    /// Estimated fraction the price of a stock moves on each tick.
+    pub fn volatility(
+        &self,
+        symbol: &str,
+    ) -> Option<f64> {
+        let changes = self.histories.get(symbol)?.changes();
+
+        (!changes.is_empty()).then(|| {
+            changes.iter().map(|change| change.abs()).sum::<f64>() /
+                changes.len() as f64
+        })
+    }
+
+    pub fn flips_detected(&self) -> u32 {
+        self.flip_votes.iter().sum()
+    }
+
+    /// Returns the tick of the market cycle most flips were seen around, if
+    /// enough of them agree.
+    fn get_flip_phase(&self) -> Option<u64> {
+        let votes_near = |phase: u64| -> u32 {
+            (0..=FLIP_PHASE_TOLERANCE * 2)
+                .map(|offset| {
+                    let tick = (phase + MARKET_CYCLE_TICKS + offset -
+                        FLIP_PHASE_TOLERANCE) %
+                        MARKET_CYCLE_TICKS;
+                    self.flip_votes[tick as usize]
+                })
+                .sum()
+        };
+
+        (0..MARKET_CYCLE_TICKS)
+            .map(|phase| (votes_near(phase), phase))
+            .filter(|(votes, _)| MIN_FLIP_VOTES <= *votes)
+            .max_by_key(|(votes, phase)| (*votes, u64::MAX - phase))
+            .map(|(_, phase)| phase)
+    }
+
+    /// Returns how many ticks are left before the forecasts may flip again.
+    pub fn ticks_until_flip(&self) -> Option<u64> {
+        let phase = self.get_flip_phase()?;
+        let current = self.tick % MARKET_CYCLE_TICKS;
+
+        Some((phase + MARKET_CYCLE_TICKS - current) % MARKET_CYCLE_TICKS)
+    }
+
+    /// Returns true if a flip is expected soon enough that new positions
+    /// would likely be caught on the wrong side.
+    pub fn is_flip_near(&self) -> bool {
+        self.ticks_until_flip()
+            .is_some_and(|ticks| ticks <= FLIP_GUARD_TICKS)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        TrendEstimator,
+        MARKET_CYCLE_TICKS,
+        MIN_FLIP_VOTES,
+        SHORT_WINDOW,
+    };
+
+    /// Prices that go up 8 ticks out of 10, and the other way around once
+    /// every cycle, starting at `flip_phase`.
+    fn make_prices(
+        ticks: u64,
+        flip_phase: u64,
+    ) -> Vec<f64> {
+        let mut price = 1000.;
+
+        (0..ticks)
+            .map(|tick| {
+                let cycle = (tick + MARKET_CYCLE_TICKS - flip_phase) /
+                    MARKET_CYCLE_TICKS;
+                let is_up = (tick % 5 != 0) == cycle.is_multiple_of(2);
+
+                price *= if is_up { 1.01 } else { 0.99 };
+                price
+            })
+            .collect()
+    }
+
+    #[test]
+    fn estimates_follow_the_trend() {
+        let mut estimator = TrendEstimator::default();
+
+        for price in make_prices(60, 60) {
+            estimator.record("ECP", price);
+            estimator.next_tick();
+        }
+
+        let forecast = estimator.forecast("ECP").unwrap();
+        assert!((forecast - 0.8).abs() < 0.05, "{}", forecast);
+        assert!((estimator.volatility("ECP").unwrap() - 0.01).abs() < 1e-3);
+        assert_eq!(estimator.forecast("MGCP"), None);
+    }
+
+    #[test]
+    fn flip_cycle_is_detected() {
+        let mut estimator = TrendEstimator::default();
+
+        for price in make_prices(MARKET_CYCLE_TICKS * 5, 30) {
+            estimator.record("ECP", price);
+            estimator.next_tick();
+        }
+
+        assert!(MIN_FLIP_VOTES <= estimator.flips_detected());
+
+        // the forecast should have followed the last flip
+        assert!(estimator.forecast("ECP").unwrap() < 0.5);
+
+        let phase = estimator.get_flip_phase().unwrap();
+        let distance = phase.abs_diff(30);
+        assert!(distance <= SHORT_WINDOW as u64, "{}", phase);
+    }
+}
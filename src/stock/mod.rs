@@ -0,0 +1,94 @@
+mod backtest;
+mod forecast;
+mod symbols;
+mod trader;
+
+pub use symbols::get_symbol;
+pub use trader::StockMode;
+use wasm_bindgen::JsValue;
+
+use crate::{
+    netscript::NsWrapper,
+    script_deploy::HGW,
+};
+
+/// Our shares of a single stock.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Position {
+    pub long_shares: f64,
+    pub long_average_price: f64,
+    pub short_shares: f64,
+    pub short_average_price: f64,
+}
+
+impl Position {
+    /// Reads our position on a stock. Returns `None` without TIX API access.
+    pub fn get(
+        ns: &NsWrapper<'_>,
+        symbol: &str,
+    ) -> Option<Position> {
+        Position::try_get(ns, symbol).ok()
+    }
+
+    /// Reads our position on a stock, or returns what Netscript threw.
+    pub fn try_get(
+        ns: &NsWrapper<'_>,
+        symbol: &str,
+    ) -> Result<Position, JsValue> {
+        let [
+            long_shares,
+            long_average_price,
+            short_shares,
+            short_average_price,
+        ] = ns.stock_get_position(symbol)?;
+
+        Ok(Position {
+            long_shares,
+            long_average_price,
+            short_shares,
+            short_average_price,
+        })
+    }
+}
+
+/// Which way we want the price of a stock to go.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StockBias {
+    #[default]
+    Neutral,
+    /// We own shares, so we want the price up.
+    Long,
+    /// We shorted shares, so we want the price down.
+    Short,
+}
+
+impl StockBias {
+    pub fn from_position(position: &Position) -> StockBias {
+        use StockBias::*;
+
+        let long_value = position.long_shares * position.long_average_price;
+        let short_value = position.short_shares * position.short_average_price;
+
+        if short_value < long_value {
+            Long
+        }
+        else if long_value < short_value {
+            Short
+        }
+        else {
+            Neutral
+        }
+    }
+
+    /// Returns true if the action should be allowed to move the price of the
+    /// stock. Grows push the price up and hacks push it down.
+    pub fn influences(
+        &self,
+        hgw: HGW,
+    ) -> bool {
+        matches!(
+            (self, hgw),
+            (StockBias::Long, HGW::Grow) | (StockBias::Short, HGW::Hack)
+        )
+    }
+}
@@ -0,0 +1,71 @@
+/// Organization names of servers and the symbols of their stocks.
+///
+/// Taken from the [source code](https://github.com/bitburner-official/bitburner-src/blob/dev/src/StockMarket/data/StockSymbols.ts).
+const ORGANIZATION_SYMBOLS: [(&str, &str); 33] = [
+    ("ECorp", "ECP"),
+    ("MegaCorp", "MGCP"),
+    ("Blade Industries", "BLD"),
+    ("Clarke Incorporated", "CLRK"),
+    ("OmniTek Incorporated", "OMTK"),
+    ("Four Sigma", "FSIG"),
+    ("KuaiGong International", "KGI"),
+    ("Fulcrum Technologies", "FLCM"),
+    ("Storm Technologies", "STM"),
+    ("DefComm", "DCOMM"),
+    ("Helios Labs", "HLS"),
+    ("VitaLife", "VITA"),
+    ("Icarus Microsystems", "ICRS"),
+    ("Universal Energy", "UNV"),
+    ("AeroCorp", "AERO"),
+    ("Omnia Cybersystems", "OMN"),
+    ("Solaris Space Systems", "SLRS"),
+    ("Global Pharmaceuticals", "GPH"),
+    ("Nova Medical", "NVMD"),
+    ("Watchdog Security", "WDS"),
+    ("LexoCorp", "LXO"),
+    ("Rho Construction", "RHOC"),
+    ("Alpha Enterprises", "APHE"),
+    ("SysCore Securities", "SYSC"),
+    ("CompuTek", "CTK"),
+    ("NetLink Technologies", "NTLK"),
+    ("Omega Software", "OMGA"),
+    ("FoodNStuff", "FNS"),
+    ("Joe's Guns", "JGN"),
+    ("Sigma Cosmetics", "SGC"),
+    ("Catalyst Ventures", "CTYS"),
+    ("Microdyne Technologies", "MDYN"),
+    ("Titan Laboratories", "TITN"),
+];
+
+/// Returns the stock symbol of the organization owning a server, if it is
+/// listed on the stock market.
+pub fn get_symbol(organization_name: &str) -> Option<&'static str> {
+    ORGANIZATION_SYMBOLS
+        .iter()
+        .find(|(org, _)| *org == organization_name)
+        .map(|(_, symbol)| *symbol)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        get_symbol,
+        ORGANIZATION_SYMBOLS,
+    };
+
+    #[test]
+    fn symbols_are_unique() {
+        for (org, symbol) in ORGANIZATION_SYMBOLS {
+            assert_eq!(get_symbol(org), Some(symbol));
+            assert_eq!(
+                ORGANIZATION_SYMBOLS
+                    .iter()
+                    .filter(|(_, sym)| *sym == symbol)
+                    .count(),
+                1
+            );
+        }
+
+        assert_eq!(get_symbol("Not A Company"), None);
+    }
+}
@@ -0,0 +1,778 @@
+use std::{
+    collections::BinaryHeap,
+    fmt::Write as _,
+};
+
+use clap::Args;
+use wasm_bindgen::JsValue;
+
+use crate::{
+    event_pool::{
+        Event,
+        EventLoop,
+        EventLoopContext,
+        EventLoopMetrics,
+        EventLoopState,
+        EventWrapper,
+        Periodic,
+        ShutdownSignal,
+        SHUTDOWN_MESSAGE,
+    },
+    netscript::{
+        Date,
+        NsWrapper,
+    },
+    stock::{
+        backtest::{
+            run_backtest,
+            TickSeries,
+        },
+        forecast::TrendEstimator,
+        Position,
+        StockBias,
+    },
+    time_consts::{
+        MILLISECOND,
+        SECOND,
+    },
+};
+
+/// Fee paid on every buy and sell.
+pub const COMMISSION: f64 = 100_000.;
+
+/// How often the prices are looked at. The market ticks about every 6
+/// seconds, or faster with bonus time, so this is short enough not to miss a
+/// tick. Samples taken within the same tick are skipped.
+const MARKET_SAMPLE_INTERVAL: f64 = SECOND;
+
+/// Don't bother buying unless the purchase is worth this many commissions.
+const MIN_PURCHASE_COMMISSIONS: f64 = 50.;
+
+/// Port listened to by a running trader for `SHUTDOWN_MESSAGE`.
+const SHUTDOWN_PORT: usize = 21;
+
+#[derive(Args, Debug, Clone)]
+pub struct StockMode {
+    #[arg(long, help = "ask the running trader to shut down then exit")]
+    stop: bool,
+    #[arg(
+        long,
+        default_value_t = 0.,
+        help = "money on home that is never spent on stocks"
+    )]
+    reserve: f64,
+    #[command(flatten)]
+    rules: TradeRules,
+    #[arg(
+        long,
+        help = "estimate the forecasts from the price history even with the \
+                4S market data"
+    )]
+    history: bool,
+    #[arg(
+        long,
+        help = "append the price of every stock to this file on each tick"
+    )]
+    record: Option<String>,
+    #[arg(long, help = "replay the ticks recorded in this file then exit")]
+    backtest: Option<String>,
+    #[arg(
+        long,
+        default_value_t = 1e9,
+        help = "money the backtest starts with"
+    )]
+    backtest_money: f64,
+}
+
+// when to open and close positions, given a forecast. this is not a doc
+// comment since clap would show it as the about of the stock subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct TradeRules {
+    #[arg(
+        long,
+        default_value_t = 0.6,
+        help = "buy once the forecast is at least this bullish"
+    )]
+    pub buy_threshold: f64,
+    #[arg(
+        long,
+        default_value_t = 0.5,
+        help = "sell once the forecast drops below this"
+    )]
+    pub sell_threshold: f64,
+    #[arg(long, help = "short stocks with a bearish forecast")]
+    pub short: bool,
+}
+
+impl TradeRules {
+    pub fn should_open_long(
+        &self,
+        forecast: f64,
+    ) -> bool {
+        self.buy_threshold <= forecast
+    }
+
+    pub fn should_open_short(
+        &self,
+        forecast: f64,
+    ) -> bool {
+        self.short && forecast <= 1. - self.buy_threshold
+    }
+
+    pub fn should_close_long(
+        &self,
+        forecast: f64,
+    ) -> bool {
+        forecast < self.sell_threshold
+    }
+
+    pub fn should_close_short(
+        &self,
+        forecast: f64,
+    ) -> bool {
+        1. - self.sell_threshold < forecast
+    }
+
+    /// Decides what to do with a stock on a tick. No position is opened when
+    /// a forecast flip is near, since it would likely end up on the wrong
+    /// side of it.
+    pub fn decide(
+        &self,
+        forecast: f64,
+        position: &Position,
+        is_flip_near: bool,
+    ) -> TradeDecision {
+        let open = if is_flip_near {
+            StockBias::Neutral
+        }
+        else if self.should_open_long(forecast) {
+            StockBias::Long
+        }
+        else if self.should_open_short(forecast) {
+            StockBias::Short
+        }
+        else {
+            StockBias::Neutral
+        };
+
+        TradeDecision {
+            close_long: 0. < position.long_shares &&
+                self.should_close_long(forecast),
+            close_short: 0. < position.short_shares &&
+                self.should_close_short(forecast),
+            open,
+        }
+    }
+}
+
+/// What the trader does with a stock on a tick. Positions are closed before
+/// new ones are opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TradeDecision {
+    pub close_long: bool,
+    pub close_short: bool,
+    /// The side to open or add to, `Neutral` to buy nothing.
+    pub open: StockBias,
+}
+
+/// How much the price is expected to move per tick, as a fraction. Positive
+/// when the price should go up.
+pub fn get_expected_return(
+    forecast: f64,
+    volatility: f64,
+) -> f64 {
+    (forecast - 0.5) * volatility
+}
+
+impl StockMode {
+    pub async fn execute(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) {
+        if self.stop {
+            ns.write_port(SHUTDOWN_PORT, SHUTDOWN_MESSAGE);
+            ns.tprint("Shutdown requested.");
+
+            return;
+        }
+
+        if let Some(filename) = &self.backtest {
+            self.do_backtest(ns, filename);
+            return;
+        }
+
+        if !ns.stock_has_tix_api_access() {
+            ns.tprint("The trader needs TIX API access.");
+            return;
+        }
+
+        let forecasts = match self.history || !ns.stock_has_4s_data_tix_api() {
+            true => {
+                ns.tprint(
+                    "Estimating the forecasts from the price history. The \
+                     trader will wait for enough ticks before trading.",
+                );
+                ForecastSource::PriceHistory(Box::default())
+            },
+            false => ForecastSource::FourSigma,
+        };
+
+        ns.disable_log("ALL");
+
+        let trader = match StockTrader::new(ns, self.clone(), forecasts) {
+            Ok(trader) => trader,
+            Err(e) => {
+                ns.tprint(&format!(
+                    "Unable to read the stock market: {}",
+                    describe_error(&e)
+                ));
+                return;
+            },
+        };
+
+        let mut event_loop = EventLoop::new(trader);
+        event_loop.add_shutdown_signal(ShutdownSignal::Port(SHUTDOWN_PORT));
+        event_loop.run(ns).await;
+    }
+
+    fn do_backtest(
+        &self,
+        ns: &NsWrapper<'_>,
+        filename: &str,
+    ) {
+        let series = match TickSeries::parse(&ns.read(filename)) {
+            Ok(series) => series,
+            Err(e) => {
+                ns.tprint(&format!("unable to read {}: {}", filename, e));
+                return;
+            },
+        };
+
+        let result = run_backtest(&series, &self.rules, self.backtest_money);
+
+        ns.tprint(&format!(
+            "\nReplayed {} tick(s) of {} stock(s)\n\
+             Profit: {:.3e} ({:+.2}%)\n\
+             Trades: {}\n\
+             Flips detected: {}",
+            series.ticks.len(),
+            series.symbols.len(),
+            result.profit(),
+            result.profit() / result.starting_money * 100.,
+            result.trades,
+            result.flips_detected,
+        ));
+    }
+}
+
+/// Where the trader gets its forecasts from.
+#[derive(Debug)]
+enum ForecastSource {
+    /// Read from the 4S market data.
+    FourSigma,
+    /// Estimated from the prices seen on each tick.
+    PriceHistory(Box<TrendEstimator>),
+}
+
+#[derive(Debug)]
+pub struct MarketTick {
+    trigger_time: f64,
+    grace_period: f64,
+}
+
+impl Event for MarketTick {
+    fn trigger_time(&self) -> f64 {
+        self.trigger_time
+    }
+
+    fn grace_period(&self) -> f64 {
+        self.grace_period
+    }
+
+    fn kind(&self) -> &'static str {
+        "MarketTick"
+    }
+}
+
+/// What the trader knows about a stock on a given tick.
+#[derive(Debug, Clone)]
+struct StockQuote {
+    symbol: String,
+    forecast: f64,
+    volatility: f64,
+    /// Price paid per share when buying long.
+    ask_price: f64,
+    /// Price paid per share when buying short.
+    bid_price: f64,
+    max_shares: f64,
+    position: Position,
+}
+
+impl StockQuote {
+    fn get(
+        ns: &NsWrapper<'_>,
+        symbol: &str,
+        forecast: f64,
+        volatility: f64,
+    ) -> Result<StockQuote, JsValue> {
+        Ok(StockQuote {
+            symbol: symbol.to_owned(),
+            forecast,
+            volatility,
+            ask_price: ns.stock_get_ask_price(symbol)?,
+            bid_price: ns.stock_get_bid_price(symbol)?,
+            max_shares: ns.stock_get_max_shares(symbol)?,
+            position: Position::try_get(ns, symbol)?,
+        })
+    }
+
+    fn expected_return(&self) -> f64 {
+        get_expected_return(self.forecast, self.volatility)
+    }
+}
+
+struct StockTrader {
+    config: StockMode,
+    forecasts: ForecastSource,
+    symbols: Vec<String>,
+    realized_profit: f64,
+    trades: usize,
+    last_log: String,
+    /// Prices seen on the last market tick, in the order of `symbols`.
+    last_prices: Vec<f64>,
+}
+
+impl StockTrader {
+    fn new(
+        ns: &NsWrapper<'_>,
+        config: StockMode,
+        forecasts: ForecastSource,
+    ) -> Result<StockTrader, JsValue> {
+        Ok(StockTrader {
+            config,
+            forecasts,
+            symbols: ns.stock_get_symbols()?,
+            realized_profit: 0.,
+            trades: 0,
+            last_log: String::new(),
+            last_prices: Vec::new(),
+        })
+    }
+
+    fn get_spendable_money(
+        &self,
+        ns: &NsWrapper<'_>,
+    ) -> f64 {
+        let money = ns.get_server_money_available("home").unwrap() as f64;
+        (money - self.config.reserve - COMMISSION).max(0.)
+    }
+
+    /// Reads the prices of every stock. Returns `None` if none of them
+    /// moved since the last tick, which means the market hasn't ticked yet.
+    fn get_new_prices(
+        &mut self,
+        ns: &NsWrapper<'_>,
+    ) -> Result<Option<Vec<f64>>, JsValue> {
+        let prices = self
+            .symbols
+            .iter()
+            .map(|symbol| ns.stock_get_price(symbol))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if prices == self.last_prices {
+            return Ok(None);
+        }
+
+        self.last_prices.clone_from(&prices);
+        Ok(Some(prices))
+    }
+
+    /// Records the prices of a new tick, then returns what we know about the
+    /// stocks we have a forecast for.
+    fn get_quotes(
+        &mut self,
+        ns: &NsWrapper<'_>,
+        prices: &[f64],
+    ) -> Result<Vec<StockQuote>, JsValue> {
+
+        if let Some(filename) = &self.config.record {
+            if !ns.file_exists(filename, &ns.get_hostname()) {
+                ns.write(filename, &TickSeries::header(&self.symbols), 'w');
+            }
+
+            ns.write(filename, &TickSeries::row(prices), 'a');
+        }
+
+        if let ForecastSource::PriceHistory(estimator) = &mut self.forecasts {
+            for (symbol, price) in self.symbols.iter().zip(prices.iter()) {
+                estimator.record(symbol, *price);
+            }
+            estimator.next_tick();
+        }
+
+        let mut quotes = Vec::new();
+
+        for symbol in self.symbols.iter() {
+            let estimate = match &self.forecasts {
+                ForecastSource::FourSigma => Some((
+                    ns.stock_get_forecast(symbol)?,
+                    ns.stock_get_volatility(symbol)?,
+                )),
+                ForecastSource::PriceHistory(estimator) => estimator
+                    .forecast(symbol)
+                    .zip(estimator.volatility(symbol)),
+            };
+
+            if let Some((forecast, volatility)) = estimate {
+                quotes.push(StockQuote::get(ns, symbol, forecast, volatility)?);
+            }
+        }
+
+        Ok(quotes)
+    }
+
+    fn is_flip_near(&self) -> bool {
+        match &self.forecasts {
+            ForecastSource::FourSigma => false,
+            ForecastSource::PriceHistory(estimator) => estimator.is_flip_near(),
+        }
+    }
+
+    fn decide(
+        &self,
+        quote: &StockQuote,
+    ) -> TradeDecision {
+        self.config.rules.decide(
+            quote.forecast,
+            &quote.position,
+            self.is_flip_near(),
+        )
+    }
+
+    fn log_trade(
+        &mut self,
+        message: String,
+    ) {
+        self.trades += 1;
+        self.last_log = message;
+    }
+
+    /// Closes the positions whose forecast turned against us.
+    fn sell_reversals(
+        &mut self,
+        ns: &NsWrapper<'_>,
+        quotes: &mut [StockQuote],
+    ) -> Result<(), JsValue> {
+        for quote in quotes.iter_mut() {
+            let position = quote.position;
+            let decision = self.decide(quote);
+
+            if decision.close_long {
+                let price =
+                    ns.stock_sell(&quote.symbol, position.long_shares)?;
+
+                if 0. < price {
+                    let profit = position.long_shares *
+                        (price - position.long_average_price) -
+                        COMMISSION;
+
+                    self.realized_profit += profit;
+                    quote.position.long_shares = 0.;
+                    self.log_trade(format!(
+                        "sold {} {} at {:.2}: {:+.3e}",
+                        position.long_shares, quote.symbol, price, profit
+                    ));
+                }
+            }
+
+            if decision.close_short {
+                let price =
+                    ns.stock_sell_short(&quote.symbol, position.short_shares)?;
+
+                if 0. < price {
+                    let profit = position.short_shares *
+                        (position.short_average_price - price) -
+                        COMMISSION;
+
+                    self.realized_profit += profit;
+                    quote.position.short_shares = 0.;
+                    self.log_trade(format!(
+                        "covered {} {} at {:.2}: {:+.3e}",
+                        position.short_shares, quote.symbol, price, profit
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Spends the money above the reserve on the stocks with the best
+    /// expected return.
+    fn buy_best(
+        &mut self,
+        ns: &NsWrapper<'_>,
+        quotes: &mut [StockQuote],
+    ) -> Result<(), JsValue> {
+        quotes.sort_by(|a, b| {
+            b.expected_return()
+                .abs()
+                .total_cmp(&a.expected_return().abs())
+        });
+
+        for quote in quotes.iter_mut() {
+            let is_long = match self.decide(quote).open {
+                StockBias::Long => true,
+                StockBias::Short => false,
+                StockBias::Neutral => continue,
+            };
+
+            let owned =
+                quote.position.long_shares + quote.position.short_shares;
+            let shares = get_shares_to_buy(
+                self.get_spendable_money(ns),
+                match is_long {
+                    true => quote.ask_price,
+                    false => quote.bid_price,
+                },
+                quote.max_shares - owned,
+            );
+
+            if shares <= 0. {
+                continue;
+            }
+
+            let price = match is_long {
+                true => ns.stock_buy(&quote.symbol, shares)?,
+                false => ns.stock_buy_short(&quote.symbol, shares)?,
+            };
+
+            if 0. < price {
+                self.realized_profit -= COMMISSION;
+                self.log_trade(format!(
+                    "{} {} {} at {:.2}",
+                    if is_long { "bought" } else { "shorted" },
+                    shares,
+                    quote.symbol,
+                    price
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn do_diagnostics(
+        &self,
+        ns: &NsWrapper<'_>,
+        quotes: &[StockQuote],
+        metrics: &EventLoopMetrics,
+    ) {
+        let mut printable = String::new();
+
+        for quote in quotes.iter() {
+            let position = quote.position;
+
+            if position.long_shares <= 0. && position.short_shares <= 0. {
+                continue;
+            }
+
+            writeln!(
+                &mut printable,
+                "| {:<5} | {:.3} | {:>10} long | {:>10} short |",
+                quote.symbol,
+                quote.forecast,
+                position.long_shares,
+                position.short_shares,
+            )
+            .unwrap();
+        }
+
+        if let ForecastSource::PriceHistory(estimator) = &self.forecasts {
+            let next_flip = match estimator.ticks_until_flip() {
+                Some(ticks) => format!("in {} tick(s)", ticks),
+                None => "unknown".to_owned(),
+            };
+
+            writeln!(
+                &mut printable,
+                "\nFlips detected: {}, next flip: {}",
+                estimator.flips_detected(),
+                next_flip
+            )
+            .unwrap();
+        }
+
+        write!(
+            &mut printable,
+            "\nRealized profit: {:.3e} over {} trade(s)\nLast trade: {}\n\n{}",
+            self.realized_profit, self.trades, self.last_log, metrics
+        )
+        .unwrap();
+
+        ns.clear_log();
+        ns.print(&printable);
+    }
+}
+
+impl EventLoopState for StockTrader {
+    type Event = MarketTick;
+
+    fn initial_run<'a>(
+        &mut self,
+        ns: &NsWrapper<'a>,
+        ctx: &mut EventLoopContext<Self::Event>,
+    ) {
+        let periodic = Periodic::new(
+            Date::now(),
+            MARKET_SAMPLE_INTERVAL,
+            |trigger_time| MarketTick {
+                trigger_time,
+                grace_period: MILLISECOND * 500.,
+            },
+        );
+
+        if ctx.add_periodic_event(periodic).is_err() {
+            ns.tprint("rejected market tick with invalid timings");
+        }
+    }
+
+    fn on_event<'a>(
+        &mut self,
+        ns: &NsWrapper<'a>,
+        _event: Self::Event,
+        ctx: &mut EventLoopContext<Self::Event>,
+    ) {
+        // this is re-armed by the event loop
+        let result = self.get_new_prices(ns).and_then(|prices| {
+            // nothing changed since the last tick
+            let Some(prices) = prices
+            else {
+                return Ok(None);
+            };
+
+            let mut quotes = self.get_quotes(ns, &prices)?;
+            self.sell_reversals(ns, &mut quotes)?;
+            self.buy_best(ns, &mut quotes)?;
+
+            Ok(Some(quotes))
+        });
+
+        match result {
+            Ok(Some(quotes)) => {
+                self.do_diagnostics(ns, &quotes, ctx.metrics())
+            },
+            Ok(None) => (),
+            Err(e) => {
+                ns.tprint(&format!(
+                    "The stock market could not be traded, shutting down: {}",
+                    describe_error(&e)
+                ));
+
+                // the loop picks this up before the next tick
+                ns.write_port(SHUTDOWN_PORT, SHUTDOWN_MESSAGE);
+            },
+        }
+    }
+
+    fn on_event_fail<'a>(
+        &mut self,
+        ns: &NsWrapper<'a>,
+        event: Self::Event,
+        ctx: &mut EventLoopContext<Self::Event>,
+    ) {
+        // a late look at the market is still good enough
+        self.on_event(ns, event, ctx);
+    }
+
+    fn post_loop_inspect<'a>(
+        &self,
+        _ns: &NsWrapper<'a>,
+        _event_heap: &BinaryHeap<EventWrapper<Self::Event>>,
+        _metrics: &EventLoopMetrics,
+    ) {
+    }
+
+    fn on_shutdown<'a>(
+        &mut self,
+        ns: &NsWrapper<'a>,
+        _event_heap: &BinaryHeap<EventWrapper<Self::Event>>,
+    ) {
+        ns.print(&format!(
+            "Shutting down. Realized profit: {:.3e} over {} trade(s). \
+             Positions are left open.",
+            self.realized_profit, self.trades
+        ));
+    }
+}
+
+/// Returns the message of what Netscript threw.
+fn describe_error(e: &JsValue) -> String {
+    e.as_string().unwrap_or_else(|| format!("{:?}", e))
+}
+
+/// Returns how many whole shares the budget buys, or zero if the purchase is
+/// too small to be worth the commission.
+pub fn get_shares_to_buy(
+    budget: f64,
+    ask_price: f64,
+    shares_left: f64,
+) -> f64 {
+    if ask_price <= 0. {
+        return 0.;
+    }
+
+    let shares = (budget / ask_price).floor().min(shares_left.floor());
+
+    if shares * ask_price < COMMISSION * MIN_PURCHASE_COMMISSIONS {
+        return 0.;
+    }
+
+    shares
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        get_shares_to_buy,
+        Position,
+        StockBias,
+        TradeRules,
+    };
+
+    #[test]
+    fn small_purchases_are_skipped() {
+        assert_eq!(get_shares_to_buy(1e6, 10., 1e9), 0.);
+        assert_eq!(get_shares_to_buy(1e9, 10., 1e9), 1e8);
+    }
+
+    #[test]
+    fn purchases_are_capped_by_max_shares() {
+        assert_eq!(get_shares_to_buy(1e12, 10., 1e6 + 0.5), 1e6);
+        assert_eq!(get_shares_to_buy(1e12, 0., 1e6), 0.);
+    }
+
+    #[test]
+    fn decisions_follow_the_rules() {
+        let rules = TradeRules {
+            buy_threshold: 0.6,
+            sell_threshold: 0.5,
+            short: true,
+        };
+        let position = Position {
+            long_shares: 10.,
+            ..Position::default()
+        };
+
+        let decision = rules.decide(0.3, &position, false);
+        assert!(decision.close_long && !decision.close_short);
+        assert_eq!(decision.open, StockBias::Short);
+
+        let decision = rules.decide(0.7, &position, false);
+        assert!(!decision.close_long);
+        assert_eq!(decision.open, StockBias::Long);
+
+        let decision = rules.decide(0.7, &position, true);
+        assert_eq!(decision.open, StockBias::Neutral);
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt::Write as _;
+
+/// A machine to be drawn in the tree.
+#[derive(Debug, Clone)]
+pub struct TreeEntry<'a> {
+    /// Path from home to the machine, as given by `Machine::get_traversal`.
+    pub traversal: &'a [String],
+    /// Written next to the hostname.
+    pub annotation: String,
+    /// Leaves that can be counted on a single line instead of being drawn,
+    /// like purchased servers.
+    pub collapsible: bool,
+}
+
+#[derive(Debug, Default)]
+struct TreeNode<'a> {
+    name: &'a str,
+    annotation: Option<&'a str>,
+    collapsible: bool,
+    children: Vec<TreeNode<'a>>,
+}
+
+impl<'a> TreeNode<'a> {
+    fn insert(
+        &mut self,
+        entry: &'a TreeEntry<'a>,
+    ) {
+        let mut node = self;
+
+        // the first hostname of every traversal is home, the root
+        for name in entry.traversal.iter().skip(1) {
+            let position =
+                node.children.iter().position(|c| c.name == name.as_str());
+
+            node = match position {
+                Some(position) => &mut node.children[position],
+                None => {
+                    node.children.push(TreeNode {
+                        name,
+                        ..TreeNode::default()
+                    });
+                    node.children.last_mut().unwrap()
+                },
+            };
+        }
+
+        node.annotation = Some(&entry.annotation);
+        node.collapsible = entry.collapsible;
+    }
+
+    fn write_children(
+        &self,
+        prefix: &str,
+        writable: &mut String,
+    ) {
+        let (collapsed, shown): (Vec<_>, Vec<_>) = self
+            .children
+            .iter()
+            .partition(|c| c.collapsible && c.children.is_empty());
+
+        let count = shown.len() + (!collapsed.is_empty()) as usize;
+
+        for (i, child) in shown.into_iter().enumerate() {
+            let is_last = i + 1 == count;
+            let branch = if is_last { "└── " } else { "├── " };
+
+            write!(writable, "{}{}{}", prefix, branch, child.name).unwrap();
+            if let Some(annotation) = child.annotation {
+                write!(writable, "  {}", annotation).unwrap();
+            }
+            writeln!(writable).unwrap();
+
+            let indent = if is_last { "    " } else { "│   " };
+            child.write_children(&format!("{}{}", prefix, indent), writable);
+        }
+
+        if !collapsed.is_empty() {
+            writeln!(
+                writable,
+                "{}└── ({} purchased servers)",
+                prefix,
+                collapsed.len()
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// Draws the machines as the spanning tree of the network, rooted at home.
+///
+/// Machines that lead to the entries but aren't entries themselves are drawn
+/// without annotations. Siblings keep the order of the entries.
+pub fn render_tree(entries: &[TreeEntry<'_>]) -> String {
+    let mut root = TreeNode {
+        name: "home",
+        ..TreeNode::default()
+    };
+
+    for entry in entries.iter() {
+        root.insert(entry);
+    }
+
+    let mut retval = "home".to_owned();
+    if let Some(annotation) = root.annotation {
+        write!(&mut retval, "  {}", annotation).unwrap();
+    }
+    retval += "\n";
+
+    root.write_children("", &mut retval);
+    retval
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        render_tree,
+        TreeEntry,
+    };
+
+    fn path(hostnames: &[&str]) -> Vec<String> {
+        hostnames.iter().map(|h| h.to_string()).collect()
+    }
+
+    #[test]
+    fn draws_branches() {
+        let paths = [
+            path(&["home", "n00dles"]),
+            path(&["home", "foodnstuff", "CSEC"]),
+            path(&["home", "pserv-0"]),
+            path(&["home", "pserv-1"]),
+        ];
+
+        let entries = paths
+            .iter()
+            .map(|traversal| TreeEntry {
+                traversal,
+                annotation: traversal.len().to_string(),
+                collapsible: traversal.last().unwrap().starts_with("pserv"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            render_tree(&entries),
+            "home\n\
+             ├── n00dles  2\n\
+             ├── foodnstuff\n\
+             │   └── CSEC  3\n\
+             └── (2 purchased servers)\n"
+        );
+    }
+
+    #[test]
+    fn draws_missing_ancestors() {
+        let paths = [path(&["home", "a", "b", "c"]), path(&["home", "d"])];
+        let entries = paths
+            .iter()
+            .map(|traversal| TreeEntry {
+                traversal,
+                annotation: "x".to_owned(),
+                collapsible: false,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            render_tree(&entries),
+            "home\n\
+             ├── a\n\
+             │   └── b\n\
+             │       └── c  x\n\
+             └── d  x\n"
+        );
+    }
+}
@@ -58,3 +58,58 @@ pub fn rational_mult_u64(
 
     x * p_uint / (q as u64)
 }
+
+/// Matches text against a shell-like pattern, where `*` stands for any run of
+/// characters and `?` for a single one. Case is ignored.
+pub fn glob_match(
+    pattern: &str,
+    text: &str,
+) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    // where the last star was in the pattern, and the text it matched up to
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            },
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            },
+            // let the last star swallow one more character
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    t = matched + 1;
+                },
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::glob_match;
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("*.lit", "hackers-starting-handbook.lit"));
+        assert!(glob_match("*.LIT", "alpha-omega.lit"));
+        assert!(!glob_match("*.lit", "contract-123.cct"));
+        assert!(glob_match("contract-???.cct", "contract-123.cct"));
+        assert!(!glob_match("contract-??.cct", "contract-123.cct"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXXbYYbc"));
+        assert!(!glob_match("a*b", "aXXbYYc"));
+    }
+}
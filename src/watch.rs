@@ -0,0 +1,277 @@
+use std::{
+    collections::BinaryHeap,
+    fmt::Write as _,
+};
+
+use crate::{
+    event_pool::{
+        Event,
+        EventLoop,
+        EventLoopContext,
+        EventLoopMetrics,
+        EventLoopState,
+        EventWrapper,
+        Periodic,
+        PeriodicStop,
+    },
+    machine::Machine,
+    netscript::{
+        Date,
+        NsWrapper,
+    },
+    time_consts::{
+        MILLISECOND,
+        SECOND,
+    },
+};
+
+#[derive(Debug)]
+pub struct Refresh {
+    trigger_time: f64,
+    grace_period: f64,
+}
+
+impl Event for Refresh {
+    fn trigger_time(&self) -> f64 {
+        self.trigger_time
+    }
+
+    fn grace_period(&self) -> f64 {
+        self.grace_period
+    }
+
+    fn kind(&self) -> &'static str {
+        "Refresh"
+    }
+}
+
+/// The state of a machine at the time of a refresh.
+#[derive(Debug, Clone, PartialEq)]
+struct MachineStatus {
+    hostname: String,
+    /// Money available, as a fraction of the max money.
+    money_ratio: f64,
+    /// Security above the min security.
+    security_delta: f64,
+    /// Free RAM, in GB.
+    free_ram: f64,
+    root: bool,
+}
+
+impl MachineStatus {
+    fn get(
+        ns: &NsWrapper<'_>,
+        machine: &Machine,
+    ) -> MachineStatus {
+        let hostname = machine.get_hostname();
+        let money = ns.get_server_money_available(hostname).unwrap();
+
+        MachineStatus {
+            hostname: hostname.to_owned(),
+            money_ratio: match machine.get_max_money() {
+                0 => 0.,
+                max_money => money as f64 / max_money as f64,
+            },
+            security_delta: ns.get_server_security_level(hostname) -
+                machine.get_min_security(),
+            free_ram: ns.get_server_max_ram(hostname) -
+                ns.get_server_used_ram(hostname),
+            root: machine.is_root(ns),
+        }
+    }
+}
+
+fn format_statuses(statuses: &[MachineStatus]) -> String {
+    let name_len = statuses
+        .iter()
+        .map(|s| s.hostname.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut printable = String::new();
+    for status in statuses.iter() {
+        let root = match status.root {
+            true => "ROOT",
+            false => "user",
+        };
+
+        writeln!(
+            &mut printable,
+            "| {: <lnl$} | {} | {: >6.2}% money | {: >+7.2} sec | {: >9.2} GB \
+             free |",
+            status.hostname,
+            root,
+            status.money_ratio * 100.,
+            status.security_delta,
+            status.free_ram,
+            lnl = name_len,
+        )
+        .unwrap();
+    }
+
+    printable
+}
+
+/// Keeps a table of the machines up to date in the log of the script.
+struct Watcher {
+    machines: Vec<Machine>,
+    interval: f64,
+    limit: WatchLimit,
+}
+
+/// When to stop refreshing the table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchLimit {
+    /// Keep refreshing until the script is killed.
+    Forever,
+    /// Stop after this many refreshes.
+    Refreshes(u64),
+    /// Stop after this many milliseconds.
+    Duration(f64),
+}
+
+impl Watcher {
+    fn do_diagnostics(
+        &self,
+        ns: &NsWrapper<'_>,
+        metrics: &EventLoopMetrics,
+    ) {
+        let statuses = self
+            .machines
+            .iter()
+            .map(|m| MachineStatus::get(ns, m))
+            .collect::<Vec<_>>();
+
+        let mut printable = format_statuses(&statuses);
+        write!(&mut printable, "\n{}", metrics).unwrap();
+
+        ns.clear_log();
+        ns.print(&printable);
+    }
+}
+
+impl EventLoopState for Watcher {
+    type Event = Refresh;
+
+    fn initial_run<'a>(
+        &mut self,
+        ns: &NsWrapper<'a>,
+        ctx: &mut EventLoopContext<Self::Event>,
+    ) {
+        let start_time = Date::now();
+        let stop = match self.limit {
+            WatchLimit::Forever => PeriodicStop::Never,
+            WatchLimit::Refreshes(count) => {
+                PeriodicStop::AfterOccurrences(count)
+            },
+            WatchLimit::Duration(duration) => {
+                PeriodicStop::At(start_time + duration)
+            },
+        };
+
+        let periodic =
+            Periodic::new(start_time, self.interval, |trigger_time| Refresh {
+                trigger_time,
+                grace_period: MILLISECOND * 500.,
+            })
+            .with_stop(stop);
+
+        if ctx.add_periodic_event(periodic).is_err() {
+            ns.tprint("rejected refresh with invalid timings");
+        }
+    }
+
+    fn on_event<'a>(
+        &mut self,
+        ns: &NsWrapper<'a>,
+        _event: Self::Event,
+        ctx: &mut EventLoopContext<Self::Event>,
+    ) {
+        // this is re-armed by the event loop
+        self.do_diagnostics(ns, ctx.metrics());
+    }
+
+    fn on_event_fail<'a>(
+        &mut self,
+        ns: &NsWrapper<'a>,
+        event: Self::Event,
+        ctx: &mut EventLoopContext<Self::Event>,
+    ) {
+        // a late refresh is better than none
+        self.on_event(ns, event, ctx);
+    }
+
+    fn post_loop_inspect<'a>(
+        &self,
+        _ns: &NsWrapper<'a>,
+        _event_heap: &BinaryHeap<EventWrapper<Self::Event>>,
+        _metrics: &EventLoopMetrics,
+    ) {
+    }
+}
+
+/// Parses a refresh interval in seconds, which has to be positive.
+pub fn parse_interval(arg: &str) -> Result<f64, String> {
+    let interval = arg.parse::<f64>().map_err(|e| e.to_string())?;
+
+    match 0. < interval && interval.is_finite() {
+        true => Ok(interval),
+        false => Err("the interval must be a positive number".to_owned()),
+    }
+}
+
+/// Refreshes a table of the machines in the log every `interval` seconds,
+/// until `limit` is reached or the script is killed.
+pub async fn watch_machines(
+    ns: &NsWrapper<'_>,
+    machines: Vec<Machine>,
+    interval: f64,
+    limit: WatchLimit,
+) {
+    ns.disable_log("ALL");
+    ns.tprint(&format!(
+        "Watching {} machines, the table is in the log of this script.",
+        machines.len()
+    ));
+
+    let watcher = Watcher {
+        machines,
+        interval: interval * SECOND,
+        limit,
+    };
+
+    let mut event_loop = EventLoop::new(watcher);
+    event_loop.run(ns).await;
+
+    ns.print("Stopped watching.");
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        format_statuses,
+        MachineStatus,
+    };
+
+    #[test]
+    fn formats_statuses() {
+        let status = |hostname: &str, root| MachineStatus {
+            hostname: hostname.to_owned(),
+            money_ratio: 0.5,
+            security_delta: 1.25,
+            free_ram: 6.,
+            root,
+        };
+
+        let printable =
+            format_statuses(&[status("n00dles", true), status("CSEC", false)]);
+
+        assert_eq!(
+            printable,
+            "| n00dles | ROOT |  50.00% money |   +1.25 sec |      6.00 GB \
+             free |\n\
+             | CSEC    | user |  50.00% money |   +1.25 sec |      6.00 GB \
+             free |\n"
+        );
+    }
+}